@@ -0,0 +1,353 @@
+//! Batch analysis of positions, one per line
+//!
+//! A position is a board of 64 `X`, `O` and `-` followed by the side to move,
+//! as in the FFO problems: `--XXXXX--OOOXX-O-OOOXXOX-OXOX... X;`. `X` is
+//! Black. Anything after the side to move is ignored.
+
+use super::board::{self, Board};
+use super::cache::SolvedCache;
+use super::game::{Color, Rules};
+use super::mobility;
+use super::search::{self, SearchInfo, SearchObserver, SearchOptions, TimeLimit};
+use super::tt::{self, TranspositionTable};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// From the point of view of `color`
+    pub board: Board,
+    pub color: Color,
+}
+
+pub fn parse_position(line: &str) -> Result<Position, String> {
+    let mut tokens = line.split_whitespace();
+    let squares = tokens.next().unwrap_or("");
+    if squares.len() != 64 || !squares.bytes().all(|c| b"XO-".contains(&c)) {
+        return Err(format!("invalid board {}", squares));
+    }
+    let b = board::parse(squares);
+    match tokens.next().map(|s| s.trim_end_matches(';')) {
+        Some("X") => Ok(Position {
+            board: b,
+            color: Color::Black,
+        }),
+        Some("O") => Ok(Position {
+            board: b.pass(),
+            color: Color::White,
+        }),
+        side => Err(format!("invalid side to move {}", side.unwrap_or(""))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Iterative deepening as `find_best_move` does
+    Limit(TimeLimit),
+    /// Exact score by `complete_search`
+    Solve,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// `u8::MAX` when the side to move has to pass
+    pub best: u8,
+    /// From the point of view of the side to move
+    pub score: i8,
+    /// Deepest completed iteration, the empties when solved
+    pub depth: u8,
+    pub exact: bool,
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// Keeps the depth of the last completed iteration
+#[derive(Default)]
+struct DepthObserver(Cell<u8>);
+
+impl SearchObserver for DepthObserver {
+    fn iteration(&self, info: &SearchInfo) {
+        self.0.set(info.depth);
+    }
+}
+
+/// `tt` is used by `Mode::Limit`, solving is reproducible with a table of its own.
+/// `cache` is used by `Mode::Solve`.
+pub fn analyze(
+    position: &Position,
+    mode: &Mode,
+    rules: Rules,
+    tt: Option<&Mutex<TranspositionTable>>,
+    cache: Option<&Mutex<SolvedCache>>,
+) -> Result<Analysis, String> {
+    let started = Instant::now();
+    let mut b = position.board.clone();
+    let passed = mobility::get_mobility(&b) == 0;
+    if passed {
+        b = b.pass();
+        if mobility::get_mobility(&b) == 0 {
+            return Err("the game is over".to_owned());
+        }
+    }
+    let observer = DepthObserver::default();
+    let result = match mode {
+        Mode::Limit(limit) => {
+            let options = SearchOptions {
+                limit: Some(limit.clone()),
+                observer: Some(&observer),
+                rules,
+                tt,
+                ..SearchOptions::default()
+            };
+            search::iterative_search(&b, &options)
+        }
+        Mode::Solve => {
+            let options = SearchOptions {
+                rules,
+                cache,
+                ..SearchOptions::default()
+            };
+            observer.0.set((b.me | b.opp).count_zeros() as u8);
+            search::complete_search_with(&b, &options)
+        }
+    };
+    Ok(Analysis {
+        best: if passed { u8::MAX } else { result.idx },
+        score: if passed { -result.score } else { result.score },
+        depth: observer.0.get(),
+        exact: result.game_end,
+        nodes: result.stats.nodes,
+        elapsed: started.elapsed(),
+    })
+}
+
+/// Analyze `lines` on `threads` threads.
+///
+/// `emit` receives the index of each line with its result, in the order of
+/// the lines.
+pub fn analyze_all(
+    lines: &[&str],
+    mode: &Mode,
+    rules: Rules,
+    threads: usize,
+    cache: Option<&Mutex<SolvedCache>>,
+    mut emit: impl FnMut(usize, &Result<Analysis, String>),
+) {
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || {
+                // reused by the searches of this thread
+                let tt = matches!(mode, Mode::Limit(_))
+                    .then(|| Mutex::new(TranspositionTable::new(tt::DEFAULT_BITS)));
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= lines.len() {
+                        break;
+                    }
+                    let result = parse_position(lines[i])
+                        .and_then(|p| analyze(&p, mode, rules, tt.as_ref(), cache));
+                    tx.send((i, result)).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        // results finishing early wait for the lines before them
+        let mut pending = BTreeMap::new();
+        let mut emitted = 0;
+        for (i, result) in rx {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&emitted) {
+                emit(emitted, &result);
+                emitted += 1;
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {}", s)),
+        }
+    }
+}
+
+/// The first line of the output, if the format has one
+pub fn header(format: Format) -> Option<&'static str> {
+    match format {
+        Format::Csv => Some("line,board,side,move,score,depth,exact,nodes,time_ms,error"),
+        Format::Json => None,
+    }
+}
+
+/// A line of the output for the position at `line_no` of the input
+pub fn format_line(
+    format: Format,
+    line_no: usize,
+    line: &str,
+    result: &Result<Analysis, String>,
+) -> String {
+    let squares = line.split_whitespace().next().unwrap_or("");
+    let side = line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("")
+        .trim_end_matches(';');
+    match (format, result) {
+        (Format::Csv, Ok(a)) => format!(
+            "{},{},{},{},{},{},{},{},{},",
+            line_no,
+            squares,
+            side,
+            move_name(a.best),
+            a.score,
+            a.depth,
+            a.exact,
+            a.nodes,
+            a.elapsed.as_millis()
+        ),
+        (Format::Csv, Err(e)) => format!(
+            "{},{},{},,,,,,,{}",
+            line_no,
+            csv_field(squares),
+            csv_field(side),
+            csv_field(e)
+        ),
+        (Format::Json, Ok(a)) => format!(
+            "{{\"line\":{},\"board\":\"{}\",\"side\":\"{}\",\"move\":\"{}\",\"score\":{},\"depth\":{},\"exact\":{},\"nodes\":{},\"time_ms\":{}}}",
+            line_no,
+            squares,
+            side,
+            move_name(a.best),
+            a.score,
+            a.depth,
+            a.exact,
+            a.nodes,
+            a.elapsed.as_millis()
+        ),
+        (Format::Json, Err(e)) => format!(
+            "{{\"line\":{},\"board\":{},\"side\":{},\"error\":{}}}",
+            line_no,
+            json_string(squares),
+            json_string(side),
+            json_string(e)
+        ),
+    }
+}
+
+fn move_name(idx: u8) -> String {
+    if idx == u8::MAX {
+        "pass".to_owned()
+    } else {
+        board::square_name(idx)
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = "\"".to_owned();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_all() {
+        // FFO #1 after 3 and 4 plies of its principal variation
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let boards: Vec<Board> = [62, 55, 56, 40]
+            .iter()
+            .scan(b, |b, idx| {
+                *b = mobility::try_put(b, *idx).unwrap();
+                Some(b.clone())
+            })
+            .collect();
+        let lines = [
+            format!("{} X;", board::serialize(&boards[3])),
+            format!("{} O; extra", board::serialize(&boards[2].pass())),
+            "not a board X;".to_owned(),
+        ];
+        let lines: Vec<&str> = lines.iter().map(|s| s.as_str()).collect();
+        let mut cache = SolvedCache::new(16);
+        cache.min_empties = 0;
+        let cache = Mutex::new(cache);
+        let mut results = vec![];
+        analyze_all(
+            &lines,
+            &Mode::Solve,
+            Rules::Standard,
+            2,
+            Some(&cache),
+            |i, r| results.push((i, r.clone())),
+        );
+        assert_eq!(cache.lock().unwrap().len(), 2);
+        assert_eq!(
+            results.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        for (i, result) in &results[..2] {
+            let a = result.as_ref().unwrap();
+            let b = parse_position(lines[*i]).unwrap().board;
+            assert_eq!(b, boards[3 - i]);
+            assert_eq!(a.score, search::complete_search(&b).score);
+            assert!(a.exact);
+            assert_eq!(a.depth, (b.me | b.opp).count_zeros() as u8);
+        }
+        assert!(results[2].1.is_err());
+
+        // the second run finds both positions in the cache
+        analyze_all(
+            &lines[..2],
+            &Mode::Solve,
+            Rules::Standard,
+            1,
+            Some(&cache),
+            |i, r| {
+                let (a, b) = (r.as_ref().unwrap(), results[i].1.as_ref().unwrap());
+                assert_eq!((a.best, a.score, a.nodes), (b.best, b.score, 0));
+            },
+        );
+
+        let csv = format_line(Format::Csv, 3, lines[2], &results[2].1);
+        assert!(csv.starts_with("3,not,a,,,,,,,invalid board"));
+        let json = format_line(Format::Json, 1, lines[0], &results[0].1);
+        assert!(json.starts_with("{\"line\":1,\"board\":\"--XXXXX--"));
+        assert!(json.contains("\"side\":\"X\""));
+    }
+}
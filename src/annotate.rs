@@ -0,0 +1,272 @@
+//! Comparison of the moves of a game with the engine
+//!
+//! Every move with an alternative is scored together with all legal moves of
+//! its position, exactly when few squares are empty. The loss of a move is the
+//! score of the best move minus the score of the move played.
+
+use super::board;
+use super::game::{Color, Game, GameError};
+use super::mobility;
+use super::search::{self, TimeLimit};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotateOptions {
+    /// Deepest iteration of positions which are not solved
+    pub max_depth: u8,
+    /// Time to score the moves of a position which is not solved
+    pub limit: TimeLimit,
+    /// Positions with at most this many empties are solved
+    pub exact_empties: u8,
+    /// A loss of at least this many discs is a blunder
+    pub blunder_loss: i8,
+}
+
+impl Default for AnnotateOptions {
+    fn default() -> AnnotateOptions {
+        AnnotateOptions {
+            max_depth: 12,
+            limit: TimeLimit::fixed(1000),
+            exact_empties: 14,
+            blunder_loss: 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    /// Index into `Game::moves`
+    pub ply: usize,
+    pub color: Color,
+    pub played: u8,
+    pub best: u8,
+    /// From the point of view of the mover
+    pub played_score: i8,
+    pub best_score: i8,
+    pub loss: i8,
+    pub exact: bool,
+    pub blunder: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerSummary {
+    /// Moves with an alternative, forced moves and passes are not counted
+    pub moves: usize,
+    pub best_moves: usize,
+    pub total_loss: i32,
+    pub blunders: usize,
+}
+
+impl PlayerSummary {
+    /// Percentage of the moves which lost nothing
+    pub fn accuracy(&self) -> f64 {
+        if self.moves == 0 {
+            100.0
+        } else {
+            self.best_moves as f64 * 100.0 / self.moves as f64
+        }
+    }
+
+    pub fn average_loss(&self) -> f64 {
+        self.total_loss as f64 / self.moves.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub moves: Vec<MoveAnnotation>,
+    pub black: PlayerSummary,
+    pub white: PlayerSummary,
+}
+
+pub fn annotate(game: &Game, options: &AnnotateOptions) -> Result<Report, GameError> {
+    let positions = game.replay()?;
+    let mut report = Report::default();
+    for (ply, (m, (b, color))) in game.moves.iter().zip(&positions).enumerate() {
+        let played = match m.idx {
+            Some(idx) if mobility::get_mobility(b).count_ones() > 1 => idx,
+            _ => continue,
+        };
+        let empties = (b.me | b.opp).count_zeros() as u8;
+        let exact = empties <= options.exact_empties;
+        let scores = if exact {
+            // far beyond any solve, i.e. no deadline
+            let unlimited = TimeLimit {
+                soft: Duration::MAX,
+                hard: Duration::MAX,
+            };
+            search::score_moves(b, game.rules, empties, &unlimited)
+        } else {
+            search::score_moves(b, game.rules, options.max_depth, &options.limit)
+        };
+        let (best, best_score) = *scores.iter().max_by_key(|(_, score)| *score).unwrap();
+        let played_score = scores
+            .iter()
+            .find(|(idx, _)| *idx == played)
+            .map(|(_, score)| *score)
+            .unwrap();
+        // the played move counts as best when it ties
+        let best = if played_score == best_score {
+            played
+        } else {
+            best
+        };
+        let loss = best_score - played_score;
+        let annotation = MoveAnnotation {
+            ply,
+            color: *color,
+            played,
+            best,
+            played_score,
+            best_score,
+            loss,
+            exact,
+            blunder: loss >= options.blunder_loss,
+        };
+        let summary = match color {
+            Color::Black => &mut report.black,
+            Color::White => &mut report.white,
+        };
+        summary.moves += 1;
+        summary.best_moves += (loss == 0) as usize;
+        summary.total_loss += loss as i32;
+        summary.blunders += annotation.blunder as usize;
+        report.moves.push(annotation);
+    }
+    Ok(report)
+}
+
+impl Report {
+    /// The score of each annotated move as the eval of the mover, as GGF
+    /// records keep them
+    pub fn embed(&self, game: &mut Game) {
+        for a in &self.moves {
+            game.moves[a.ply].eval = Some(a.played_score as f64);
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut s = "  # move best score loss\n".to_owned();
+        for a in &self.moves {
+            s.push_str(&format!(
+                "{:3} {} {}   {}   {:+3}  {:3}{}{}\n",
+                a.ply + 1,
+                color_char(a.color),
+                board::square_name(a.played),
+                board::square_name(a.best),
+                a.played_score,
+                a.loss,
+                if a.exact { "" } else { " ~" },
+                if a.blunder { " ??" } else { "" }
+            ));
+        }
+        for (name, summary) in [("Black", &self.black), ("White", &self.white)] {
+            s.push_str(&format!(
+                "{} : {} moves, accuracy {:.1}%, average loss {:.2}, {} blunders\n",
+                name,
+                summary.moves,
+                summary.accuracy(),
+                summary.average_loss(),
+                summary.blunders
+            ));
+        }
+        s
+    }
+
+    pub fn to_json(&self) -> String {
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .map(|a| {
+                format!(
+                    "{{\"ply\":{},\"color\":\"{}\",\"move\":\"{}\",\"best\":\"{}\",\"score\":{},\"best_score\":{},\"loss\":{},\"exact\":{},\"blunder\":{}}}",
+                    a.ply + 1,
+                    color_name(a.color),
+                    board::square_name(a.played),
+                    board::square_name(a.best),
+                    a.played_score,
+                    a.best_score,
+                    a.loss,
+                    a.exact,
+                    a.blunder
+                )
+            })
+            .collect();
+        let summary = |s: &PlayerSummary| {
+            format!(
+                "{{\"moves\":{},\"best_moves\":{},\"accuracy\":{:.1},\"average_loss\":{:.2},\"blunders\":{}}}",
+                s.moves,
+                s.best_moves,
+                s.accuracy(),
+                s.average_loss(),
+                s.blunders
+            )
+        };
+        format!(
+            "{{\"moves\":[{}],\"black\":{},\"white\":{}}}",
+            moves.join(","),
+            summary(&self.black),
+            summary(&self.white)
+        )
+    }
+}
+
+fn color_char(color: Color) -> char {
+    match color {
+        Color::Black => 'B',
+        Color::White => 'W',
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::Black => "black",
+        Color::White => "white",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Move;
+
+    #[test]
+    fn test_annotate() {
+        // 10 empties of FFO #1, Black to move
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let start = [62, 55, 56, 40]
+            .iter()
+            .fold(b, |b, idx| mobility::try_put(&b, *idx).unwrap());
+        let mut game = Game::new(start.clone(), Color::Black);
+        // Black plays the best moves, White always its first legal move
+        let mut b = start.clone();
+        let mut color = Color::Black;
+        loop {
+            let idx = match mobility::legal_moves(&b).next() {
+                Some(first) if color == Color::White => Some(first),
+                Some(_) => Some(search::complete_search(&b).idx),
+                None if mobility::get_mobility(&b.pass()) != 0 => None,
+                None => break,
+            };
+            game.moves.push(Move::new(color, idx));
+            b = idx.map(|idx| mobility::put(&b, idx)).unwrap_or(b.pass());
+            color = color.opposite();
+        }
+
+        let report = annotate(&game, &AnnotateOptions::default()).unwrap();
+        assert!(report.moves.iter().all(|a| a.exact && a.loss >= 0));
+        assert_eq!(report.black.accuracy(), 100.0);
+        assert!(report.white.total_loss > 0);
+        // the value of the start only changes by the losses of White
+        let value = search::complete_search(&start).score;
+        assert_eq!(
+            game.final_score().unwrap() as i32,
+            value as i32 + report.white.total_loss
+        );
+
+        report.embed(&mut game);
+        let first = &report.moves[0];
+        assert_eq!(game.moves[first.ply].eval, Some(value as f64));
+        assert!(report.to_json().contains("\"black\":{\"moves\""));
+    }
+}
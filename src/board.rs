@@ -1,65 +1,286 @@
 use super::mobility;
-use std::collections::HashMap;
+use super::render::Renderer;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Board {
-    pub me: u64,
-    pub opp: u64,
+/// Squares of the largest board, 10x10
+pub const MAX_SQUARES: usize = 100;
+
+/// Integer holding one bit per square
+pub trait Bits:
+    Copy
+    + Default
+    + Eq
+    + Hash
+    + Debug
+    + BitAnd<Output = Self>
+    + BitAndAssign
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + BitXor<Output = Self>
+    + BitXorAssign
+    + Not<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+    + Sub<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    /// All the bits folded into 64, for hashing
+    fn fold(self) -> u64;
 }
 
-impl Board {
-    pub fn new() -> Board {
+macro_rules! impl_bits {
+    ($($t:ty),*) => {
+        $(
+            impl Bits for $t {
+                const ZERO: $t = 0;
+                const ONE: $t = 1;
+
+                #[inline]
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+
+                #[inline]
+                fn trailing_zeros(self) -> u32 {
+                    <$t>::trailing_zeros(self)
+                }
+
+                #[inline]
+                fn fold(self) -> u64 {
+                    self as u128 as u64 ^ (self as u128 >> 64) as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_bits!(u16, u64, u128);
+
+/// Width of the board, the integer backing it and its move generation
+pub trait Size: Copy + Default + Eq + Hash + Debug + 'static {
+    type Bits: Bits;
+    const N: u32;
+    const SQUARES: u32 = Self::N * Self::N;
+    /// Every square of the board
+    const FULL: Self::Bits;
+    /// Squares with `x == 0`
+    const FIRST_COLUMN: Self::Bits;
+
+    fn mobility(b: &Board<Self>) -> Self::Bits;
+    /// Discs flipped by a move at `position`, a single bit
+    fn flip(b: &Board<Self>, position: Self::Bits) -> Self::Bits;
+}
+
+macro_rules! impl_size {
+    ($(#[$attr:meta])* $name:ident, $t:ty, $n:expr, $mobility:path, $flip:path) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name;
+
+        impl Size for $name {
+            type Bits = $t;
+            const N: u32 = $n;
+            const FULL: $t = <$t>::MAX >> (<$t>::BITS - $n * $n);
+            const FIRST_COLUMN: $t = {
+                let mut column = 0;
+                let mut y = 0;
+                while y < $n {
+                    column |= 1 << (y * $n);
+                    y += 1;
+                }
+                column
+            };
+
+            #[inline]
+            fn mobility(b: &Board<Self>) -> $t {
+                $mobility(b)
+            }
+
+            #[inline]
+            fn flip(b: &Board<Self>, position: $t) -> $t {
+                $flip(b, position)
+            }
+        }
+    };
+}
+
+impl_size!(
+    Size4,
+    u16,
+    4,
+    mobility::get_mobility_shifts,
+    mobility::get_flip_shifts
+);
+impl_size!(
+    Size6,
+    u64,
+    6,
+    mobility::get_mobility_shifts,
+    mobility::get_flip_shifts
+);
+impl_size!(
+    /// The standard board, with SIMD move generation
+    Size8,
+    u64,
+    8,
+    mobility::get_mobility_8x8,
+    mobility::get_flip_8x8
+);
+impl_size!(
+    /// Grand Othello
+    Size10,
+    u128,
+    10,
+    mobility::get_mobility_shifts,
+    mobility::get_flip_shifts
+);
+
+/// Discs of the side to move and of its opponent.
+///
+/// Square `(x, y)` is bit `y * N + x`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board<S: Size = Size8> {
+    pub me: S::Bits,
+    pub opp: S::Bits,
+}
+
+impl<S: Size> Board<S> {
+    /// The four center discs, Black to move.
+    ///
+    /// `Board::new` is the 8x8 position right after Black's f5.
+    pub fn initial() -> Board<S> {
+        let bit = |x: u32, y: u32| S::Bits::ONE << (y * S::N + x);
+        let c = S::N / 2;
         Board {
-            me: 1 << 27,
-            opp: (1 << 28) | (7 << 35),
+            me: bit(c, c - 1) | bit(c - 1, c),
+            opp: bit(c - 1, c - 1) | bit(c, c),
         }
     }
 
-    pub fn pass(&self) -> Board {
+    pub fn pass(&self) -> Board<S> {
         Board {
             me: self.opp,
             opp: self.me,
         }
     }
+
+    pub fn empties(&self) -> u32 {
+        S::SQUARES - (self.me | self.opp).count_ones()
+    }
 }
 
-impl std::fmt::Display for Board {
+impl Board {
+    pub fn new() -> Board {
+        Board {
+            me: 1 << 27,
+            opp: (1 << 28) | (7 << 35),
+        }
+    }
+
+    /// All 8 boards which are equivalent to this one by rotation and reflection.
+    ///
+    /// The `i`-th board is transformed by `transform(_, i)`.
+    pub fn symmetries(&self) -> [Board; 8] {
+        std::array::from_fn(|i| Board {
+            me: transform(self.me, i),
+            opp: transform(self.opp, i),
+        })
+    }
+
+    /// The representative of the symmetry class, usable as a hash key.
+    pub fn canonical(&self) -> Board {
+        self.symmetries()
+            .into_iter()
+            .min_by_key(|b| (b.me, b.opp))
+            .unwrap()
+    }
+}
+
+/// One of the 8 rotations and reflections of a bitboard, `symmetry` in `0..8`
+pub fn transform(mut x: u64, symmetry: usize) -> u64 {
+    if symmetry & 1 != 0 {
+        x = flip_vertical(x);
+    }
+    if symmetry & 2 != 0 {
+        x = flip_horizontal(x);
+    }
+    if symmetry & 4 != 0 {
+        x = flip_diagonal(x);
+    }
+    x
+}
+
+// https://www.chessprogramming.org/Flipping_Mirroring_and_Rotating
+fn flip_vertical(x: u64) -> u64 {
+    x.swap_bytes()
+}
+
+fn flip_horizontal(x: u64) -> u64 {
+    x.reverse_bits().swap_bytes()
+}
+
+fn flip_diagonal(mut x: u64) -> u64 {
+    let k1 = 0x5500550055005500;
+    let k2 = 0x3333000033330000;
+    let k4 = 0x0f0f0f0f00000000;
+    let mut t = k4 & (x ^ (x << 28));
+    x ^= t ^ (t >> 28);
+    t = k2 & (x ^ (x << 14));
+    x ^= t ^ (t >> 14);
+    t = k1 & (x ^ (x << 7));
+    x ^= t ^ (t >> 7);
+    x
+}
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
+/// `{:#}` appends the serialized board and the raw bitboards.
+impl<S: Size> std::fmt::Display for Board<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut map = HashMap::new();
-        map.insert("ｘ".to_string(), self.me);
-        map.insert("ｏ".to_string(), self.opp);
-        map.insert("＿".to_string(), mobility::get_mobility(self));
-        write!(
-            f,
-            "{}\nSer : {}\nDbg : {:?}\n",
-            to_str(&map),
-            serialize(self),
-            self
-        )
+        let renderer = Renderer {
+            debug: f.alternate(),
+            ..Renderer::default()
+        };
+        write!(f, "{}", renderer.render(self))
     }
 }
 
-fn to_str(map: &HashMap<String, u64>) -> String {
-    let mut buf = "ーーーーーーーーーー\n".to_string();
-    for y in 0..8 {
-        buf.push_str("| ");
-        for x in 0..8 {
-            let idx = y * 8 + x;
-            let mut found = false;
-            for (s, b) in map {
-                if b >> idx & 1 == 1 {
-                    buf.push_str(s);
-                    found = true;
-                }
-            }
-            if !found {
-                buf.push('　');
+/// `d3` style square name
+pub fn square_name(idx: u8) -> String {
+    square_name_in::<Size8>(idx)
+}
+
+/// `square_name` on a board of another size, with rows from 1 to N
+pub fn square_name_in<S: Size>(idx: u8) -> String {
+    let idx = idx as u32;
+    format!("{}{}", (b'a' + (idx % S::N) as u8) as char, idx / S::N + 1)
+}
+
+/// Parse a square name, ignoring case
+pub fn parse_square(s: &str) -> Option<u8> {
+    match s.as_bytes() {
+        [x, y] => {
+            let x = x.to_ascii_lowercase().wrapping_sub(b'a');
+            let y = y.wrapping_sub(b'1');
+            if x < 8 && y < 8 {
+                Some(y * 8 + x)
+            } else {
+                None
             }
         }
-        buf.push_str(" |\n")
+        _ => None,
     }
-    buf.push_str("ーーーーーーーーーー");
-    buf
 }
 
 // --XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--
@@ -76,12 +297,13 @@ pub fn parse(s: &str) -> Board {
     Board { me, opp }
 }
 
-pub fn serialize(b: &Board) -> String {
+pub fn serialize<S: Size>(b: &Board<S>) -> String {
     let mut s = "".to_owned();
-    for idx in 0..64 {
-        if b.me >> idx & 1 == 1 {
+    for idx in 0..S::SQUARES {
+        let position = S::Bits::ONE << idx;
+        if b.me & position != S::Bits::ZERO {
             s.push('X');
-        } else if b.opp >> idx & 1 == 1 {
+        } else if b.opp & position != S::Bits::ZERO {
             s.push('O');
         } else {
             s.push('-');
@@ -0,0 +1,324 @@
+//! Solved positions kept on disk
+//!
+//! Exact scores and best moves of endgame positions, keyed by the canonical
+//! board so that rotated and reflected positions share an entry. The file is
+//! a header followed by fixed size records, each with its own checksum.
+
+use super::board::{self, Board};
+use super::game::Rules;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 4] = b"RVSC";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 12;
+const RECORD_SIZE: usize = 24;
+
+/// 2^20 positions, 24MiB on disk
+pub const DEFAULT_MAX_ENTRIES: usize = 1 << 20;
+/// Fewer empties are solved faster than the cache is worth
+pub const DEFAULT_MIN_EMPTIES: u8 = 14;
+
+#[derive(Debug)]
+pub enum CacheError {
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CacheError::Io(e) => write!(f, "failed to access the cache : {}", e),
+            CacheError::Format(s) => write!(f, "invalid cache : {}", s),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<std::io::Error> for CacheError {
+    fn from(e: std::io::Error) -> CacheError {
+        CacheError::Io(e)
+    }
+}
+
+/// Exact result of a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solved {
+    pub score: i8,
+    /// The reply of the opponent when the side to move has to pass,
+    /// `u8::MAX` at the game end
+    pub best: u8,
+}
+
+pub struct SolvedCache {
+    /// Keyed by the canonical board, the best move in its orientation
+    entries: HashMap<(Board, Rules), Solved>,
+    /// Keys of `entries` by their empties, oldest first
+    by_empties: Vec<VecDeque<(Board, Rules)>>,
+    /// The oldest entry with the fewest empties is dropped beyond this
+    pub max_entries: usize,
+    /// Positions with fewer empties are not stored
+    pub min_empties: u8,
+    /// Records dropped by the integrity check when loaded
+    pub corrupted: usize,
+}
+
+impl SolvedCache {
+    pub fn new(max_entries: usize) -> SolvedCache {
+        SolvedCache {
+            entries: HashMap::new(),
+            by_empties: vec![VecDeque::new(); 65],
+            max_entries,
+            min_empties: DEFAULT_MIN_EMPTIES,
+            corrupted: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, b: &Board, rules: Rules) -> Option<Solved> {
+        let canonical = b.canonical();
+        let solved = self.entries.get(&(canonical.clone(), rules))?;
+        // the symmetry which turns the canonical board back into `b`
+        let symmetry = canonical.symmetries().iter().position(|s| s == b)?;
+        Some(Solved {
+            best: transform_square(solved.best, symmetry),
+            ..*solved
+        })
+    }
+
+    pub fn insert(&mut self, b: &Board, rules: Rules, solved: Solved) {
+        if empties(b) < self.min_empties || self.max_entries == 0 {
+            return;
+        }
+        let symmetries = b.symmetries();
+        let canonical = b.canonical();
+        let symmetry = symmetries.iter().position(|s| *s == canonical).unwrap();
+        let key = (canonical, rules);
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            let fewest = self
+                .by_empties
+                .iter_mut()
+                .find(|keys| !keys.is_empty())
+                .unwrap();
+            if empties(&fewest[0].0) > empties(&key.0) {
+                return;
+            }
+            let evicted = fewest.pop_front().unwrap();
+            self.entries.remove(&evicted);
+        }
+        self.add(
+            key,
+            Solved {
+                best: transform_square(solved.best, symmetry),
+                ..solved
+            },
+        );
+    }
+
+    fn add(&mut self, key: (Board, Rules), solved: Solved) {
+        let empties = empties(&key.0) as usize;
+        if self.entries.insert(key.clone(), solved).is_none() {
+            self.by_empties[empties].push_back(key);
+        }
+    }
+
+    /// Canonical positions with their results
+    pub fn entries(&self) -> impl Iterator<Item = (&Board, Rules, Solved)> {
+        self.entries.iter().map(|((b, r), s)| (b, *r, *s))
+    }
+
+    /// An empty cache if the file does not exist yet
+    pub fn load(path: &str, max_entries: usize) -> Result<SolvedCache, CacheError> {
+        match std::fs::File::open(path) {
+            Ok(file) => SolvedCache::read(std::io::BufReader::new(file), max_entries),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SolvedCache::new(max_entries)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Written to a temporary file first, so that an interrupted save keeps the old file
+    pub fn save(&self, path: &str) -> Result<(), CacheError> {
+        let tmp = format!("{}.tmp", path);
+        let mut w = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+        self.write(&mut w)?;
+        w.flush()?;
+        drop(w);
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn read(mut r: impl Read, max_entries: usize) -> Result<SolvedCache, CacheError> {
+        let mut bytes = vec![];
+        r.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(CacheError::Format("not a solved position cache".to_owned()));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(CacheError::Format(format!("unknown version {}", version)));
+        }
+        let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        if bytes.len() != HEADER_SIZE + count * RECORD_SIZE {
+            return Err(CacheError::Format(format!(
+                "{} bytes for {} records",
+                bytes.len(),
+                count
+            )));
+        }
+        let mut cache = SolvedCache::new(max_entries);
+        for record in bytes[HEADER_SIZE..].chunks(RECORD_SIZE) {
+            match parse_record(record) {
+                Some((b, rules, solved)) if cache.entries.len() < max_entries => {
+                    cache.add((b, rules), solved);
+                }
+                Some(_) => {}
+                None => cache.corrupted += 1,
+            }
+        }
+        Ok(cache)
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> Result<(), CacheError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&[0; 2])?;
+        w.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for ((b, rules), solved) in &self.entries {
+            let mut record = [0; RECORD_SIZE];
+            record[0..8].copy_from_slice(&b.me.to_le_bytes());
+            record[8..16].copy_from_slice(&b.opp.to_le_bytes());
+            record[16] = solved.score as u8;
+            record[17] = solved.best;
+            record[18] = (*rules == Rules::Anti) as u8;
+            let sum = checksum(&record[..20]);
+            record[20..].copy_from_slice(&sum.to_le_bytes());
+            w.write_all(&record)?;
+        }
+        Ok(())
+    }
+}
+
+/// None if the checksum or the contents are wrong
+fn parse_record(record: &[u8]) -> Option<(Board, Rules, Solved)> {
+    if checksum(&record[..20]).to_le_bytes() != record[20..] {
+        return None;
+    }
+    let b = Board {
+        me: u64::from_le_bytes(record[0..8].try_into().unwrap()),
+        opp: u64::from_le_bytes(record[8..16].try_into().unwrap()),
+    };
+    let score = record[16] as i8;
+    let best = record[17];
+    let rules = match record[18] {
+        0 => Rules::Standard,
+        1 => Rules::Anti,
+        _ => return None,
+    };
+    let valid = b.me & b.opp == 0
+        && (-64..=64).contains(&score)
+        && (best == u8::MAX || (best < 64 && (b.me | b.opp) >> best & 1 == 0))
+        && b.canonical() == b;
+    valid.then_some((b, rules, Solved { score, best }))
+}
+
+/// 32-bit FNV-1a
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |h, b| {
+        (h ^ *b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn empties(b: &Board) -> u8 {
+    (b.me | b.opp).count_zeros() as u8
+}
+
+fn transform_square(idx: u8, symmetry: usize) -> u8 {
+    if idx < 64 {
+        board::transform(1 << idx, symmetry).trailing_zeros() as u8
+    } else {
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let mut cache = SolvedCache::new(2);
+        cache.insert(
+            &b,
+            Rules::Standard,
+            Solved {
+                score: 18,
+                best: 62,
+            },
+        );
+        for s in b.symmetries() {
+            let best = cache.get(&s, Rules::Standard).unwrap().best;
+            // the best move of every symmetry is the image of g8
+            let i = b.symmetries().iter().position(|t| *t == s).unwrap();
+            assert_eq!(best, transform_square(62, i));
+        }
+        assert_eq!(cache.get(&b, Rules::Anti), None);
+
+        let mut bytes = vec![];
+        cache.write(&mut bytes).unwrap();
+        let loaded = SolvedCache::read(&bytes[..], 2).unwrap();
+        assert_eq!(
+            loaded.get(&b, Rules::Standard),
+            Some(Solved {
+                score: 18,
+                best: 62
+            })
+        );
+        bytes[HEADER_SIZE + 16] ^= 1;
+        let corrupted = SolvedCache::read(&bytes[..], 2).unwrap();
+        assert_eq!((corrupted.len(), corrupted.corrupted), (0, 1));
+        assert!(SolvedCache::read(&bytes[..HEADER_SIZE + 1], 2).is_err());
+
+        // the board with the fewest empties is dropped first
+        cache.min_empties = 10;
+        let fewer = crate::mobility::put(&b, 62);
+        let more = Board {
+            me: b.me & !(1 << 2),
+            opp: b.opp,
+        };
+        cache.insert(
+            &fewer,
+            Rules::Standard,
+            Solved {
+                score: -18,
+                best: 55,
+            },
+        );
+        assert_eq!(cache.len(), 2);
+        cache.insert(&more, Rules::Standard, Solved { score: 0, best: 2 });
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&fewer, Rules::Standard), None);
+        assert!(cache.get(&b, Rules::Standard).is_some());
+        assert!(cache.get(&more, Rules::Standard).is_some());
+        // and nothing with even fewer empties gets in
+        let fewest = crate::mobility::put(&fewer, 55);
+        cache.insert(
+            &fewest,
+            Rules::Standard,
+            Solved {
+                score: 18,
+                best: 56,
+            },
+        );
+        assert_eq!(cache.get(&fewest, Rules::Standard), None);
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,85 @@
+use super::board::Board;
+use super::search::TimeLimit;
+use std::time::Duration;
+
+/// Time kept in reserve so that the clock never runs out
+const SAFETY_MARGIN: Duration = Duration::from_millis(100);
+
+/// A player's game clock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    pub remaining: Duration,
+    /// Added after every move
+    pub increment: Duration,
+}
+
+impl Clock {
+    pub fn new(remaining: Duration, increment: Duration) -> Clock {
+        Clock {
+            remaining,
+            increment,
+        }
+    }
+
+    /// Split the remaining time over the moves left in the game.
+    ///
+    /// Positions around 20 empties get the largest share,
+    /// as an exact solve there decides the game.
+    pub fn allocate(&self, b: &Board) -> TimeLimit {
+        let empties = (b.me | b.opp).count_zeros();
+        let moves_left = empties.div_ceil(2);
+        let weight = match empties {
+            0..=12 => 0.5,
+            13..=24 => 2.0,
+            25..=44 => 1.2,
+            _ => 0.6,
+        };
+        let available = self.remaining.saturating_sub(SAFETY_MARGIN);
+        let soft = (available / moves_left.max(1)).mul_f64(weight) + self.increment.mul_f64(0.8);
+        let hard = (soft * 3).min(available / 3 + self.increment.mul_f64(0.8));
+        TimeLimit {
+            soft: soft.min(hard),
+            hard: hard.min(available),
+        }
+    }
+
+    /// Account for a move which took `used`.
+    ///
+    /// Returns by how much the move overran the remaining time, in which case
+    /// only the increment is left.
+    pub fn consume(&mut self, used: Duration) -> Option<Duration> {
+        let overrun = used.checked_sub(self.remaining).filter(|d| !d.is_zero());
+        self.remaining = self.remaining.saturating_sub(used) + self.increment;
+        overrun
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board;
+
+    #[test]
+    fn test_allocate() {
+        let clock = Clock::new(Duration::from_secs(60), Duration::from_secs(1));
+        let opening = clock.allocate(&Board::new());
+        let endgame = clock.allocate(&board::parse(
+            "--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--",
+        ));
+        assert!(opening.soft < endgame.soft);
+        assert!(opening.soft <= opening.hard && endgame.soft <= endgame.hard);
+        assert!(endgame.hard < clock.remaining / 2);
+
+        let nearly_flagged = Clock::new(Duration::from_millis(150), Duration::ZERO);
+        assert!(nearly_flagged.allocate(&Board::new()).hard <= Duration::from_millis(50));
+
+        let mut clock = clock;
+        assert_eq!(clock.consume(Duration::from_secs(10)), None);
+        assert_eq!(clock.remaining, Duration::from_secs(51));
+        assert_eq!(
+            clock.consume(Duration::from_secs(53)),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(clock.remaining, Duration::from_secs(1));
+    }
+}
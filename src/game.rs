@@ -0,0 +1,177 @@
+use super::board::{self, Board};
+use super::mobility;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+/// Which side wins at the end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Rules {
+    #[default]
+    Standard,
+    /// Anti-reversi, the player with fewer discs wins
+    Anti,
+}
+
+impl std::str::FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rules, String> {
+        match s {
+            "standard" => Ok(Rules::Standard),
+            "anti" => Ok(Rules::Anti),
+            _ => Err(format!("unknown rules {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Player {
+    pub name: String,
+    pub rating: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    pub color: Color,
+    /// `None` means pass
+    pub idx: Option<u8>,
+    /// Evaluation by the player, from the mover's point of view
+    pub eval: Option<f64>,
+    pub seconds: Option<f64>,
+}
+
+impl Move {
+    pub fn new(color: Color, idx: Option<u8>) -> Move {
+        Move {
+            color,
+            idx,
+            eval: None,
+            seconds: None,
+        }
+    }
+}
+
+/// A game record, independent of the file format it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub place: Option<String>,
+    pub date: Option<String>,
+    pub black: Player,
+    pub white: Player,
+    pub time_control: Option<String>,
+    pub rules: Rules,
+    /// Start position from the point of view of `start_color`
+    pub start: Board,
+    pub start_color: Color,
+    pub moves: Vec<Move>,
+    /// Final disc difference from Black's point of view
+    pub score: Option<f64>,
+    /// How the game ended when it was not played out, e.g. `r` for resignation
+    pub ending: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    WrongColor { ply: usize },
+    IllegalMove { ply: usize, idx: u8 },
+    IllegalPass { ply: usize },
+    AfterEnd { ply: usize },
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GameError::WrongColor { ply } => write!(f, "move #{} is played out of turn", ply + 1),
+            GameError::IllegalMove { ply, idx } => write!(
+                f,
+                "move #{} {} is illegal",
+                ply + 1,
+                board::square_name(*idx)
+            ),
+            GameError::IllegalPass { ply } => {
+                write!(f, "move #{} passes while a move is available", ply + 1)
+            }
+            GameError::AfterEnd { ply } => {
+                write!(f, "move #{} is played after the end of the game", ply + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl Game {
+    pub fn new(start: Board, start_color: Color) -> Game {
+        Game {
+            place: None,
+            date: None,
+            black: Player::default(),
+            white: Player::default(),
+            time_control: None,
+            rules: Rules::Standard,
+            start,
+            start_color,
+            moves: vec![],
+            score: None,
+            ending: None,
+        }
+    }
+
+    /// Positions before each move and after the last one, with the side to move.
+    ///
+    /// Every move is checked against the rules.
+    pub fn replay(&self) -> Result<Vec<(Board, Color)>, GameError> {
+        let mut b = self.start.clone();
+        let mut color = self.start_color;
+        let mut positions = vec![(b.clone(), color)];
+        for (ply, m) in self.moves.iter().enumerate() {
+            if m.color != color {
+                return Err(GameError::WrongColor { ply });
+            }
+            if mobility::get_mobility(&b) == 0 && mobility::get_mobility(&b.pass()) == 0 {
+                return Err(GameError::AfterEnd { ply });
+            }
+            b = match m.idx {
+                Some(idx) => {
+                    mobility::try_put(&b, idx).map_err(|_| GameError::IllegalMove { ply, idx })?
+                }
+                None if mobility::get_mobility(&b) == 0 => b.pass(),
+                None => return Err(GameError::IllegalPass { ply }),
+            };
+            color = color.opposite();
+            positions.push((b.clone(), color));
+        }
+        Ok(positions)
+    }
+
+    /// Disc difference of the final position from Black's point of view.
+    ///
+    /// Empty squares go to the winner.
+    pub fn final_score(&self) -> Result<i8, GameError> {
+        let (b, color) = self.replay()?.pop().unwrap();
+        let diff = b.me.count_ones() as i8 - b.opp.count_ones() as i8;
+        let empties = (b.me | b.opp).count_zeros() as i8;
+        let diff = match diff.cmp(&0) {
+            std::cmp::Ordering::Greater => diff + empties,
+            std::cmp::Ordering::Less => diff - empties,
+            std::cmp::Ordering::Equal => 0,
+        };
+        Ok(match color {
+            Color::Black => diff,
+            Color::White => -diff,
+        })
+    }
+}
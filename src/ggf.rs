@@ -0,0 +1,393 @@
+//! Generic Game Format used by GGS
+//!
+//! https://skatgame.net/mburo/ggsa/ggf
+
+use super::board::{self, Board};
+use super::game::{Color, Game, GameError, Move, Rules};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GgfError {
+    Syntax(String),
+    Game(GameError),
+}
+
+impl std::fmt::Display for GgfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GgfError::Syntax(s) => write!(f, "invalid GGF : {}", s),
+            GgfError::Game(e) => write!(f, "invalid game : {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GgfError {}
+
+impl From<GameError> for GgfError {
+    fn from(e: GameError) -> GgfError {
+        GgfError::Game(e)
+    }
+}
+
+fn syntax_error<T>(s: &str) -> Result<T, GgfError> {
+    Err(GgfError::Syntax(s.to_owned()))
+}
+
+/// Parse every `(;...;)` game in the input, checking each of them by replaying.
+pub fn parse_all(s: &str) -> Result<Vec<Game>, GgfError> {
+    let mut games = vec![];
+    let mut rest = s;
+    while let Some(start) = rest.find("(;") {
+        let end = match game_end(&rest[start..]) {
+            Some(end) => start + end,
+            None => return syntax_error("missing ;)"),
+        };
+        games.push(parse(&rest[start..end + 2])?);
+        rest = &rest[end + 2..];
+    }
+    Ok(games)
+}
+
+/// Index of the `;)` ending the game, skipping the property values
+fn game_end(s: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with('[') {
+            i += 1 + value_end(&s[i + 1..])?;
+        } else if s[i..].starts_with(";)") {
+            return Some(i);
+        }
+        i += s[i..].chars().next().unwrap().len_utf8();
+    }
+    None
+}
+
+/// Index of the `]` closing a value, skipping those escaped by `\`
+fn value_end(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ']' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::new();
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unescaped.push(c);
+            escaped = false;
+        }
+    }
+    unescaped
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// Parse a single `(;...;)` game.
+pub fn parse(s: &str) -> Result<Game, GgfError> {
+    let body = match s
+        .trim()
+        .strip_prefix("(;")
+        .and_then(|s| s.strip_suffix(";)"))
+    {
+        Some(body) => body,
+        None => return syntax_error("a game must be enclosed by (; and ;)"),
+    };
+
+    let mut game = Game::new(Board::initial(), Color::Black);
+    let mut rest = body.trim_start();
+    while !rest.is_empty() {
+        let open = match rest.find('[') {
+            Some(open) => open,
+            None => return syntax_error(rest),
+        };
+        let close = match value_end(&rest[open + 1..]) {
+            Some(close) => open + 1 + close,
+            None => return syntax_error("missing ]"),
+        };
+        let key = rest[..open].trim();
+        let value = unescape(&rest[open + 1..close]);
+        let value = value.as_str();
+        rest = rest[close + 1..].trim_start();
+
+        match key {
+            "GM" if !value.eq_ignore_ascii_case("othello") => {
+                return syntax_error(&format!("unsupported game {}", value))
+            }
+            "PC" => game.place = Some(value.to_owned()),
+            "DT" => game.date = Some(value.to_owned()),
+            "PB" => game.black.name = value.to_owned(),
+            "PW" => game.white.name = value.to_owned(),
+            "RB" => game.black.rating = Some(parse_number(value)?),
+            "RW" => game.white.rating = Some(parse_number(value)?),
+            "TI" => game.time_control = Some(value.to_owned()),
+            "TY" => {
+                game.rules = match value.trim() {
+                    "8" => Rules::Standard,
+                    "8a" => Rules::Anti,
+                    _ => return syntax_error(&format!("unsupported type {}", value)),
+                }
+            }
+            "RE" => {
+                let mut elems = value.splitn(2, ':');
+                game.score = Some(parse_number(elems.next().unwrap())?);
+                game.ending = elems.next().map(|s| s.to_owned());
+            }
+            "BO" => {
+                let (start, color) = parse_board(value)?;
+                game.start = start;
+                game.start_color = color;
+            }
+            "B" => game.moves.push(parse_move(Color::Black, value)?),
+            "W" => game.moves.push(parse_move(Color::White, value)?),
+            _ => {}
+        }
+    }
+    game.replay()?;
+    Ok(game)
+}
+
+fn parse_number(s: &str) -> Result<f64, GgfError> {
+    s.trim()
+        .parse()
+        .or_else(|_| syntax_error(&format!("not a number {}", s)))
+}
+
+/// `8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *`
+fn parse_board(s: &str) -> Result<(Board, Color), GgfError> {
+    let mut chars = s.chars().filter(|c| !c.is_whitespace());
+    if chars.next() != Some('8') {
+        return syntax_error(&format!("unsupported board {}", s));
+    }
+    let chars: Vec<char> = chars.collect();
+    if chars.len() != 65 {
+        return syntax_error(&format!("board must have 64 squares and a color : {}", s));
+    }
+    let (mut black, mut white) = (0u64, 0u64);
+    for (idx, c) in chars[..64].iter().enumerate() {
+        match c {
+            '*' => black |= 1 << idx,
+            'O' => white |= 1 << idx,
+            '-' => {}
+            _ => return syntax_error(&format!("unknown square {}", c)),
+        }
+    }
+    match chars[64] {
+        '*' => Ok((
+            Board {
+                me: black,
+                opp: white,
+            },
+            Color::Black,
+        )),
+        'O' => Ok((
+            Board {
+                me: white,
+                opp: black,
+            },
+            Color::White,
+        )),
+        c => syntax_error(&format!("unknown color {}", c)),
+    }
+}
+
+/// `d3/1.50/12.3`, where the eval and the time are optional
+fn parse_move(color: Color, s: &str) -> Result<Move, GgfError> {
+    let mut elems = s.split('/');
+    let square = elems.next().unwrap().trim();
+    let idx = if square.eq_ignore_ascii_case("pa") {
+        None
+    } else {
+        match board::parse_square(square) {
+            Some(idx) => Some(idx),
+            None => return syntax_error(&format!("unknown move {}", square)),
+        }
+    };
+    let eval = match elems.next() {
+        Some(e) if !e.trim().is_empty() => Some(parse_number(e)?),
+        _ => None,
+    };
+    let seconds = match elems.next() {
+        Some(t) if !t.trim().is_empty() => Some(parse_time(t)?),
+        _ => None,
+    };
+    Ok(Move {
+        color,
+        idx,
+        eval,
+        seconds,
+    })
+}
+
+/// `12.3`, `1:02.5` or `1:00:00`
+fn parse_time(s: &str) -> Result<f64, GgfError> {
+    let mut seconds = 0.0;
+    for elem in s.split(':') {
+        seconds = seconds * 60.0 + parse_number(elem)?;
+    }
+    Ok(seconds)
+}
+
+pub fn serialize(game: &Game) -> String {
+    let mut s = "(;GM[Othello]".to_owned();
+    if let Some(place) = &game.place {
+        s.push_str(&format!("PC[{}]", escape(place)));
+    }
+    if let Some(date) = &game.date {
+        s.push_str(&format!("DT[{}]", escape(date)));
+    }
+    s.push_str(&format!(
+        "PB[{}]PW[{}]",
+        escape(&game.black.name),
+        escape(&game.white.name)
+    ));
+    if let Some(rating) = game.black.rating {
+        s.push_str(&format!("RB[{:.2}]", rating));
+    }
+    if let Some(rating) = game.white.rating {
+        s.push_str(&format!("RW[{:.2}]", rating));
+    }
+    if let Some(time_control) = &game.time_control {
+        s.push_str(&format!("TI[{}]", escape(time_control)));
+    }
+    s.push_str(match game.rules {
+        Rules::Standard => "TY[8]",
+        Rules::Anti => "TY[8a]",
+    });
+    if let Some(score) = game.score {
+        s.push_str(&format!("RE[{:+.3}", score));
+        if let Some(ending) = &game.ending {
+            s.push_str(&format!(":{}", escape(ending)));
+        }
+        s.push(']');
+    }
+    s.push_str(&format!(
+        "BO[{}]",
+        serialize_board(&game.start, game.start_color)
+    ));
+    for m in &game.moves {
+        s.push_str(match m.color {
+            Color::Black => "B[",
+            Color::White => "W[",
+        });
+        s.push_str(
+            &m.idx
+                .map(board::square_name)
+                .unwrap_or_else(|| "pa".to_owned()),
+        );
+        if m.eval.is_some() || m.seconds.is_some() {
+            s.push('/');
+            if let Some(eval) = m.eval {
+                s.push_str(&format!("{:.2}", eval));
+            }
+            if let Some(seconds) = m.seconds {
+                s.push_str(&format!("/{:.2}", seconds));
+            }
+        }
+        s.push(']');
+    }
+    s.push_str(";)");
+    s
+}
+
+fn serialize_board(b: &Board, color: Color) -> String {
+    let (black, white) = match color {
+        Color::Black => (b.me, b.opp),
+        Color::White => (b.opp, b.me),
+    };
+    let mut s = "8".to_owned();
+    for idx in 0..64 {
+        if idx % 8 == 0 {
+            s.push(' ');
+        }
+        s.push(if black >> idx & 1 == 1 {
+            '*'
+        } else if white >> idx & 1 == 1 {
+            'O'
+        } else {
+            '-'
+        });
+    }
+    s.push(' ');
+    s.push(match color {
+        Color::Black => '*',
+        Color::White => 'O',
+    });
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = "(;GM[Othello]PC[GGS/os]DT[2003.12.15_13:24:03.MST]PB[alice]PW[bob]RB[2197.00]RW[2124.00]TI[5:00//02:00]TY[8]RE[-4.000]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[d3//0.01]W[c3/0.21/0.01]B[f5//1:02]W[e3/-1.00/0.01]B[c2]W[e6]B[d7]W[b1]B[d2]W[c4]B[b4]W[e7]B[f7]W[g5]B[g4]W[c6]B[f3]W[e2]B[c1]W[g8]B[b5]W[e8]B[f1]W[d1]B[f6]W[a5]B[a4]W[g2]B[a6]W[b2]B[f8]W[c5]B[b3]W[f2]B[g3]W[a2]B[d8]W[g7]B[g1]W[b6]B[c7]W[h2]B[g6]W[a7]B[f4]W[c8]B[a1]W[a3]B[d6]W[h4]B[e1]W[h6]B[b7]W[a8]B[h5]W[h3]B[h7]W[b8]B[h1]W[h8];)";
+
+    #[test]
+    fn test_parse() {
+        let game = parse(GAME).unwrap();
+        assert_eq!(game.black.name, "alice");
+        assert_eq!(game.white.rating, Some(2124.0));
+        assert_eq!(game.time_control.as_deref(), Some("5:00//02:00"));
+        assert_eq!(game.score, Some(-4.0));
+        assert_eq!(game.moves.len(), 60);
+        assert_eq!(game.moves[1].eval, Some(0.21));
+        assert_eq!(game.moves[2].seconds, Some(62.0));
+        assert_eq!(game.start, Board::initial());
+        assert_eq!(game.final_score(), Ok(-4));
+
+        assert_eq!(parse(&serialize(&game)).unwrap(), game);
+        let anti = Game {
+            rules: Rules::Anti,
+            ..game.clone()
+        };
+        assert!(serialize(&anti).contains("TY[8a]"));
+        assert_eq!(parse(&serialize(&anti)).unwrap(), anti);
+        assert_eq!(
+            parse_all(&format!("{}\n{}\n", GAME, GAME)).unwrap().len(),
+            2
+        );
+
+        let mut escaped = game.clone();
+        escaped.black.name = "a]b\\;)".to_owned();
+        let s = serialize(&escaped);
+        assert!(s.contains("PB[a\\]b\\\\;)]"));
+        assert_eq!(parse_all(&s).unwrap(), vec![escaped]);
+        for ty in ["TY[8r]", "TY[88a]", "TY[a8]"] {
+            assert!(parse(&GAME.replace("TY[8]", ty)).is_err(), "{}", ty);
+        }
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        let illegal = GAME.replace("W[c3/0.21/0.01]", "W[a1]");
+        assert_eq!(
+            parse(&illegal),
+            Err(GgfError::Game(GameError::IllegalMove { ply: 1, idx: 0 }))
+        );
+        let out_of_turn = GAME.replace("W[c3/0.21/0.01]", "B[c3]");
+        assert_eq!(
+            parse(&out_of_turn),
+            Err(GgfError::Game(GameError::WrongColor { ply: 1 }))
+        );
+        let pass = GAME.replace("W[c3/0.21/0.01]", "W[pa]");
+        assert_eq!(
+            parse(&pass),
+            Err(GgfError::Game(GameError::IllegalPass { ply: 1 }))
+        );
+        let after_end = GAME.replace("W[h8];)", "W[h8]B[pa];)");
+        assert_eq!(
+            parse(&after_end),
+            Err(GgfError::Game(GameError::AfterEnd { ply: 60 }))
+        );
+    }
+}
@@ -0,0 +1,25 @@
+// threads are not available on wasm32-unknown-unknown
+#[cfg(not(target_arch = "wasm32"))]
+pub mod analyze;
+pub mod annotate;
+pub mod board;
+pub mod cache;
+pub mod clock;
+pub mod game;
+pub mod ggf;
+pub mod mobility;
+pub mod opening;
+pub mod ordering;
+pub mod perft;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ponder;
+pub mod puzzle;
+pub mod render;
+pub mod search;
+pub mod strength;
+pub mod svg;
+pub mod time;
+pub mod transcript;
+pub mod tt;
+pub mod wasm;
+pub mod wthor;
@@ -1,8 +1,21 @@
 use getopts::Options;
-
-mod board;
-mod mobility;
-mod search;
+use rand::SeedableRng;
+use reversi_rs::analyze::{self, Format};
+use reversi_rs::annotate::{self, AnnotateOptions};
+use reversi_rs::board::{Board, Size, Size10, Size4, Size6, Size8};
+use reversi_rs::cache::{self, SolvedCache};
+use reversi_rs::clock::Clock;
+use reversi_rs::game::{Color, Game, Move, Rules};
+use reversi_rs::opening::{self, OpeningOptions};
+use reversi_rs::ordering::{self, OrderingStage, OrderingStats};
+use reversi_rs::ponder::{self, PonderMode};
+use reversi_rs::puzzle::{self, PuzzleOptions};
+use reversi_rs::render::{Renderer, Style};
+use reversi_rs::search::SearchState;
+use reversi_rs::strength::{self, Strength};
+use reversi_rs::tt::{self, TranspositionTable};
+use reversi_rs::{board, ggf, mobility, perft, search, svg, transcript, wthor};
+use std::sync::{Arc, Mutex};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -10,10 +23,183 @@ fn main() {
     opts.optflag("", "solve-ffo", "Solve FFO");
     opts.optopt("", "ffo-start", "FFO start #", "NUMBER");
     opts.optopt("", "ffo-end", "FFO end #", "NUMBER");
+    opts.optopt(
+        "",
+        "ordering",
+        "Move ordering stages of tt, killers, history, shallow and fastest",
+        "tt,killers,fastest",
+    );
+    opts.optopt(
+        "",
+        "bench-midgame",
+        "Compare full and aspiration windows to DEPTH on midgame positions",
+        "DEPTH",
+    );
+    opts.optopt(
+        "",
+        "aspiration",
+        "Half width of the aspiration window, 0 for the full window",
+        "WIDTH",
+    );
+    opts.optopt(
+        "",
+        "cache",
+        "Keep solved positions of --solve and --solve-ffo in FILE",
+        "FILE",
+    );
+    opts.optopt("", "cache-max", "Most positions kept in the cache", "COUNT");
+    opts.optopt("", "cache-export", "Print the positions of a cache", "FILE");
+    opts.optopt(
+        "",
+        "analyze",
+        "Analyze the positions of FILE, or stdin for -",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "analyze-time",
+        "Search time per position, 1000 by default",
+        "MS",
+    );
+    opts.optflag("", "analyze-solve", "Solve the positions exactly");
+    opts.optopt(
+        "",
+        "annotate",
+        "Report the mistakes of the games in a GGF or transcript FILE",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "annotate-time",
+        "Time to score the moves of an unsolved position, 1000 by default",
+        "MS",
+    );
+    opts.optopt(
+        "",
+        "puzzles",
+        "Print endgame puzzles found in a GGF or transcript FILE",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "puzzle-games",
+        "Print endgame puzzles of COUNT self-play games at --level",
+        "COUNT",
+    );
+    opts.optopt(
+        "",
+        "puzzle-empties",
+        "Empties of puzzles, 10-24 by default",
+        "MIN-MAX",
+    );
+    opts.optopt(
+        "",
+        "gen-openings",
+        "Print COUNT balanced random openings",
+        "COUNT",
+    );
+    opts.optopt(
+        "",
+        "opening-plies",
+        "Random moves of generated openings, 8 by default",
+        "PLIES",
+    );
+    opts.optopt(
+        "",
+        "opening-depth",
+        "Search depth scoring generated openings, 10 by default",
+        "DEPTH",
+    );
+    opts.optopt(
+        "",
+        "opening-score",
+        "Keep openings scored within SCORE of even, 2 by default",
+        "SCORE",
+    );
+    opts.optopt(
+        "",
+        "openings",
+        "Start self-play from an opening of FILE, random by --seed",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "opening",
+        "Start from opening NUMBER of --openings",
+        "NUMBER",
+    );
+    opts.optopt(
+        "",
+        "format",
+        "Output of --analyze or --annotate",
+        "csv|json|text",
+    );
+    opts.optopt("", "threads", "Positions analyzed in parallel", "COUNT");
     opts.optopt("b", "board", "Start board", "BOARD_EXPRESSION");
+    opts.optopt("t", "transcript", "Start after these moves", "f5d6c3...");
+    opts.optopt("", "perft", "Count leaf nodes to DEPTH", "DEPTH");
+    opts.optflag("", "perft-hash", "Use the hashed perft");
+    opts.optopt("", "size", "Board size for --perft", "4|6|8|10");
+    opts.optopt(
+        "",
+        "solve-size",
+        "Solve the initial position of a smaller or larger board",
+        "4|6|8|10",
+    );
+    opts.optopt("", "export-ggf", "Append the played game to FILE", "FILE");
+    opts.optopt("", "export-html", "Write the played game as a page", "FILE");
+    opts.optopt("", "svg", "Render the start position and exit", "FILE");
+    opts.optflag("", "solve", "Solve the start position and exit");
+    opts.optopt("", "rules", "Rules of the game", "standard|anti");
+    opts.optopt("", "time", "Clock of each player", "SECONDS");
+    opts.optopt("", "increment", "Clock increment per move", "SECONDS");
+    opts.optopt(
+        "",
+        "ponder",
+        "Search on the opponent's time",
+        "predicted|all",
+    );
+    opts.optopt("", "level", "Engine strength from 1 to 10", "LEVEL");
+    opts.optopt(
+        "",
+        "margin",
+        "Pick randomly among moves within MARGIN",
+        "MARGIN",
+    );
+    opts.optopt("", "seed", "Random seed for varied play", "SEED");
+    opts.optopt("", "style", "Board style", "ascii|unicode|ansi");
+    opts.optflag("", "debug-board", "Print serialized boards");
+    opts.optopt("", "import-ggf", "Replay and check games in FILE", "FILE");
+    opts.optopt(
+        "",
+        "wthor",
+        "Replay and summarize a WTHOR database",
+        "FILE.wtb",
+    );
+    opts.optopt("", "wthor-players", "WTHOR player names", "FILE.jou");
+    opts.optopt(
+        "",
+        "wthor-tournaments",
+        "WTHOR tournament names",
+        "FILE.trn",
+    );
     let matches = opts
         .parse(&args[1..])
         .unwrap_or_else(|f| panic!("{}", f.to_string()));
+    let ordering = matches
+        .opt_str("ordering")
+        .map(|s| ordering::parse(&s).unwrap_or_else(|e| panic!("{}", e)));
+    let cache_max = matches
+        .opt_str("cache-max")
+        .map(|s| s.parse().unwrap())
+        .unwrap_or(cache::DEFAULT_MAX_ENTRIES);
+    let cache = matches
+        .opt_str("cache")
+        .map(|path| (Mutex::new(load_cache(&path, cache_max)), path));
+    let openings = matches
+        .opt_str("openings")
+        .map(|path| load_openings(&path))
+        .unwrap_or_default();
     if matches.opt_present("solve-ffo") {
         solve_ffo(
             matches
@@ -26,39 +212,727 @@ fn main() {
                 .unwrap_or_else(|| "100".to_owned())
                 .parse()
                 .unwrap(),
+            ordering.as_deref(),
+            cache.as_ref().map(|(c, _)| c),
+        );
+        save_cache(&cache);
+    } else if let Some(path) = matches.opt_str("analyze") {
+        let mode = if matches.opt_present("analyze-solve") {
+            analyze::Mode::Solve
+        } else {
+            analyze::Mode::Limit(search::TimeLimit::fixed(
+                matches
+                    .opt_str("analyze-time")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(1000),
+            ))
+        };
+        run_analysis(
+            &path,
+            &mode,
+            matches
+                .opt_str("rules")
+                .map(|s| s.parse().unwrap_or_else(|e| panic!("{}", e)))
+                .unwrap_or_default(),
+            matches
+                .opt_str("format")
+                .map(|s| s.parse().unwrap_or_else(|e| panic!("{}", e)))
+                .unwrap_or(Format::Csv),
+            matches
+                .opt_str("threads")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(1),
+            cache.as_ref().map(|(c, _)| c),
+        );
+        save_cache(&cache);
+    } else if let Some(path) = matches.opt_str("annotate") {
+        let mut options = AnnotateOptions::default();
+        if let Some(ms) = matches.opt_str("annotate-time") {
+            options.limit = search::TimeLimit::fixed(ms.parse().unwrap());
+        }
+        let json = match matches.opt_str("format").as_deref() {
+            None | Some("text") => false,
+            Some("json") => true,
+            Some(format) => panic!("unknown format {}", format),
+        };
+        annotate_games(&path, &options, json, matches.opt_str("export-ggf"));
+    } else if matches.opt_present("puzzles") || matches.opt_present("puzzle-games") {
+        let mut options = PuzzleOptions::default();
+        if let Some(range) = matches.opt_str("puzzle-empties") {
+            let (min, max) = range
+                .split_once('-')
+                .unwrap_or_else(|| panic!("invalid range {}", range));
+            options.min_empties = min.parse().unwrap();
+            options.max_empties = max.parse().unwrap();
+        }
+        mine_puzzles(
+            matches
+                .opt_str("puzzles")
+                .map(|path| load_games(&path))
+                .unwrap_or_default(),
+            matches
+                .opt_str("puzzle-games")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(0),
+            &Strength::level(
+                matches
+                    .opt_str("level")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(3),
+            ),
+            matches.opt_str("seed").map(|s| s.parse().unwrap()),
+            &openings,
+            &options,
+        );
+    } else if let Some(count) = matches.opt_str("gen-openings") {
+        let mut options = OpeningOptions::default();
+        if let Some(plies) = matches.opt_str("opening-plies") {
+            options.plies = plies.parse().unwrap();
+        }
+        if let Some(depth) = matches.opt_str("opening-depth") {
+            options.depth = depth.parse().unwrap();
+        }
+        if let Some(score) = matches.opt_str("opening-score") {
+            options.max_score = score.parse().unwrap();
+        }
+        if let Some(rules) = matches.opt_str("rules") {
+            options.rules = rules.parse().unwrap_or_else(|e| panic!("{}", e));
+        }
+        generate_openings(
+            count.parse().unwrap(),
+            matches.opt_str("seed").map(|s| s.parse().unwrap()),
+            &options,
+        );
+    } else if let Some(path) = matches.opt_str("cache-export") {
+        export_cache(&path);
+    } else if let Some(depth) = matches.opt_str("bench-midgame") {
+        bench_midgame(
+            depth.parse().unwrap(),
+            matches
+                .opt_str("aspiration")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(search::ASPIRATION_WINDOW),
+            ordering.as_deref(),
+        );
+    } else if let Some(depth) = matches.opt_str("perft") {
+        let depth = depth.parse().unwrap();
+        match matches.opt_str("size").map(|s| s.parse().unwrap()) {
+            None | Some(8) if matches.opt_present("perft-hash") => {
+                let b = Board::initial();
+                run_perft(depth, |d| perft::perft_hashed(&b, d));
+            }
+            None | Some(8) => {
+                let b = Board::<Size8>::initial();
+                run_perft(depth, |d| perft::perft(&b, d));
+            }
+            Some(4) => run_perft(depth, |d| perft::perft(&Board::<Size4>::initial(), d)),
+            Some(6) => run_perft(depth, |d| perft::perft(&Board::<Size6>::initial(), d)),
+            Some(10) => run_perft(depth, |d| perft::perft(&Board::<Size10>::initial(), d)),
+            Some(size) => panic!("unsupported size {}", size),
+        }
+    } else if let Some(size) = matches.opt_str("solve-size") {
+        match size.parse().unwrap() {
+            4 => solve_sized::<Size4>(),
+            6 => solve_sized::<Size6>(),
+            8 => solve_sized::<Size8>(),
+            10 => solve_sized::<Size10>(),
+            size => panic!("unsupported size {}", size),
+        }
+    } else if let Some(path) = matches.opt_str("import-ggf") {
+        import_ggf(&path);
+    } else if let Some(path) = matches.opt_str("wthor") {
+        summarize_wthor(
+            &path,
+            matches.opt_str("wthor-players"),
+            matches.opt_str("wthor-tournaments"),
         );
     } else {
-        run_game(matches.opt_str("board"));
+        if matches.opt_present("board") && matches.opt_present("transcript") {
+            panic!("--board and --transcript are both a start position, give only one");
+        }
+        let mut game = if let Some(s) = matches.opt_str("board") {
+            Game::new(board::parse(&s), Color::Black)
+        } else if !openings.is_empty() {
+            let i = match matches.opt_str("opening") {
+                Some(n) => match n.parse::<usize>() {
+                    Ok(n) if (1..=openings.len()).contains(&n) => n - 1,
+                    _ => panic!(
+                        "--opening must be a number from 1 to {}, not {}",
+                        openings.len(),
+                        n
+                    ),
+                },
+                None => {
+                    use rand::Rng;
+                    let seed = matches.opt_str("seed").map(|s| s.parse().unwrap());
+                    rng_of(seed).gen_range(0..openings.len())
+                }
+            };
+            eprintln!("opening #{} of {}", i + 1, openings.len());
+            openings[i].clone()
+        } else {
+            // `Board::new` is the position after f5
+            let s = matches
+                .opt_str("transcript")
+                .unwrap_or_else(|| "f5".to_owned());
+            transcript::parse(&s).unwrap_or_else(|e| panic!("{}", e))
+        };
+        if let Some(rules) = matches.opt_str("rules") {
+            game.rules = rules.parse().unwrap_or_else(|e| panic!("{}", e));
+        }
+        if matches.opt_present("solve") {
+            let (b, color) = game.replay().unwrap().pop().unwrap();
+            println!(
+                "{}",
+                Renderer {
+                    color: Some(color),
+                    ..Renderer::default()
+                }
+                .render(&b)
+            );
+            let started = std::time::Instant::now();
+            let options = search::SearchOptions {
+                rules: game.rules,
+                ordering: ordering.as_deref(),
+                observer: Some(&search::ConsoleObserver),
+                cache: cache.as_ref().map(|(c, _)| c),
+                ..search::SearchOptions::default()
+            };
+            let result = search::complete_search_with(&b, &options);
+            save_cache(&cache);
+            println!(
+                "{:?} {:+} by {}, search finished in {}ms",
+                game.rules,
+                result.score,
+                board::square_name(result.idx),
+                started.elapsed().as_millis()
+            );
+            println!("{}", result.stats);
+            for (depth, nodes) in result.stats.depth_nodes.iter().enumerate() {
+                println!("depth {:2}: {:12} nodes", depth, nodes);
+            }
+        } else if let Some(path) = matches.opt_str("svg") {
+            let (b, color) = game.replay().unwrap().pop().unwrap();
+            let options = svg::SvgOptions {
+                last_move: game.moves.last().and_then(|m| m.idx),
+                legal_moves: true,
+                ..svg::SvgOptions::default()
+            };
+            write_file(&path, &svg::render(&b, color, &options));
+        } else {
+            let renderer = Renderer {
+                style: matches
+                    .opt_str("style")
+                    .map(|s| s.parse().unwrap_or_else(|e| panic!("{}", e)))
+                    .unwrap_or(Style::Unicode),
+                debug: matches.opt_present("debug-board"),
+                ..Renderer::default()
+            };
+            let clock = matches.opt_str("time").map(|t| {
+                let seconds = |s: String| std::time::Duration::from_secs_f64(s.parse().unwrap());
+                Clock::new(
+                    seconds(t),
+                    matches
+                        .opt_str("increment")
+                        .map(seconds)
+                        .unwrap_or_default(),
+                )
+            });
+            let strength = if matches.opt_present("level") || matches.opt_present("margin") {
+                let level = matches
+                    .opt_str("level")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(strength::MAX_LEVEL);
+                let mut strength = Strength::level(level);
+                if let Some(margin) = matches.opt_str("margin") {
+                    strength.margin = margin.parse().unwrap();
+                }
+                Some(strength)
+            } else {
+                None
+            };
+            let options = PlayOptions {
+                clock,
+                strength,
+                seed: matches.opt_str("seed").map(|s| s.parse().unwrap()),
+                ponder: matches
+                    .opt_str("ponder")
+                    .map(|s| s.parse().unwrap_or_else(|e| panic!("{}", e))),
+                ggf_path: matches.opt_str("export-ggf"),
+                html_path: matches.opt_str("export-html"),
+                renderer,
+                ordering,
+            };
+            run_game(game, options);
+        }
     }
 }
 
-fn run_game(b_str: Option<String>) {
-    let mut b = b_str
-        .map(|s| board::parse(&s))
-        .unwrap_or_else(board::Board::new);
-    println!("{}\n", b);
+struct PlayOptions {
+    /// Fixed 30 seconds per move without a clock
+    clock: Option<Clock>,
+    /// Full strength deterministic search without this
+    strength: Option<Strength>,
+    seed: Option<u64>,
+    ponder: Option<PonderMode>,
+    ggf_path: Option<String>,
+    html_path: Option<String>,
+    renderer: Renderer,
+    ordering: Option<Vec<OrderingStage>>,
+}
+
+/// Engine self-play continuing the given game
+fn run_game(mut game: Game, options: PlayOptions) {
+    let (mut b, mut color) = game.replay().unwrap().pop().unwrap();
+    game.place = Some("reversi-rs".to_owned());
+    game.black.name = "reversi-rs".to_owned();
+    game.white.name = "reversi-rs".to_owned();
+    let mut renderer = Renderer {
+        color: Some(color),
+        last_move: game.moves.last().and_then(|m| m.idx),
+        ..options.renderer
+    };
+    println!("{}\n", renderer.render(&b));
+    if let Some(clock) = &options.clock {
+        // GGF style main time / increment / extension
+        let m_ss = |d: std::time::Duration| format!("{}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+        game.time_control = Some(format!(
+            "{}/{}/",
+            m_ss(clock.remaining),
+            m_ss(clock.increment)
+        ));
+    }
+    let mut clocks = [options.clock.clone(), options.clock];
+    let states: [Arc<Mutex<SearchState>>; 2] = Default::default();
+    // shared by the search and the ponder of each side
+    let tts = [(); 2].map(|_| Arc::new(Mutex::new(TranspositionTable::new(tt::DEFAULT_BITS))));
+    let mut ponders: [Option<ponder::Ponder>; 2] = [None, None];
+    let mut rng = rng_of(options.seed);
     loop {
         let mob = mobility::get_mobility(&b);
         let opp_mob = mobility::get_mobility(&b.pass());
         if mob > 0 {
-            let best_move = search::find_best_move(&b, 30000);
-            b = mobility::put(&b, best_move);
+            let clock = &mut clocks[color as usize];
+            let state = &states[color as usize];
+            let tt = &tts[color as usize];
+            if let Some(p) = ponders[color as usize].take() {
+                p.stop();
+                if state.lock().unwrap().get(&b).is_some() {
+                    println!("ponderhit");
+                }
+            }
+            // the ponder stopped on the opponent's time
+            let started = std::time::Instant::now();
+            let limit = match clock {
+                Some(clock) => clock.allocate(&b),
+                None => search::TimeLimit::fixed(30000),
+            };
+            let search_options = search::SearchOptions {
+                limit: Some(limit.clone()),
+                state: options.ponder.map(|_| state.as_ref()),
+                tt: Some(tt),
+                observer: Some(&search::ConsoleObserver),
+                rules: game.rules,
+                ordering: options.ordering.as_deref(),
+                ..search::SearchOptions::default()
+            };
+            let best_move = match &options.strength {
+                Some(strength) => strength::choose_move(&b, game.rules, strength, &limit, &mut rng),
+                None => search::iterative_search(&b, &search_options).idx,
+            };
+            let used = started.elapsed();
+            if let Some(clock) = clock {
+                if let Some(overrun) = clock.consume(used) {
+                    println!(
+                        "{:?} overran the clock by {:.3}s",
+                        color,
+                        overrun.as_secs_f64()
+                    );
+                }
+                println!("{:?} clock : {:.1}s", color, clock.remaining.as_secs_f64());
+            }
+            b = mobility::try_put(&b, best_move).unwrap_or_else(|e| panic!("{}", e));
+            game.moves.push(Move {
+                seconds: Some(used.as_secs_f64()),
+                ..Move::new(color, Some(best_move))
+            });
+            // both sides run in this process, where a ponder competes for the
+            // CPU with the other side's search, so only Black ponders
+            if let (Some(mode), Color::Black) = (options.ponder, color) {
+                state.lock().unwrap().clear();
+                ponders[color as usize] = Some(ponder::start(
+                    &b,
+                    mode,
+                    game.rules,
+                    state.clone(),
+                    tt.clone(),
+                ));
+            }
         } else if opp_mob > 0 {
             println!("pass");
             b = b.pass();
+            game.moves.push(Move::new(color, None));
         } else {
             break;
         }
-        println!("{}\n", b);
+        color = color.opposite();
+        renderer.color = Some(color);
+        renderer.last_move = game.moves.last().and_then(|m| m.idx);
+        println!("{}\n", renderer.render(&b));
+    }
+
+    let score = game.final_score().unwrap();
+    game.score = Some(score as f64);
+    println!("{} {:+}", transcript::serialize(&game, false), score);
+    if game.rules == Rules::Anti {
+        println!("anti-reversi, fewer discs win");
+    }
+    if let Some(path) = options.ggf_path {
+        append_ggf(&path, &game);
+    }
+    if let Some(path) = options.html_path {
+        write_file(&path, &svg::render_game_html(&game).unwrap());
+    }
+}
+
+fn append_ggf(path: &str, game: &Game) {
+    use std::io::Write;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("failed to open {} : {}", path, e));
+    writeln!(file, "{}", ggf::serialize(game)).unwrap();
+}
+
+/// Games of a GGF file, or one transcript per line
+fn load_games(path: &str) -> Vec<Game> {
+    let s =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read {} : {}", path, e));
+    if s.trim_start().starts_with("(;") {
+        ggf::parse_all(&s).unwrap_or_else(|e| panic!("{}", e))
+    } else {
+        s.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| transcript::parse(line).unwrap_or_else(|e| panic!("{}", e)))
+            .collect()
+    }
+}
+
+fn annotate_games(path: &str, options: &AnnotateOptions, json: bool, ggf_path: Option<String>) {
+    for (i, mut game) in load_games(path).into_iter().enumerate() {
+        let report = annotate::annotate(&game, options).unwrap_or_else(|e| panic!("{}", e));
+        if json {
+            println!("{}", report.to_json());
+        } else {
+            println!("#{} {} vs {}", i + 1, game.black.name, game.white.name);
+            print!("{}", report.to_text());
+        }
+        if let Some(path) = &ggf_path {
+            report.embed(&mut game);
+            append_ggf(path, &game);
+        }
+    }
+}
+
+/// Openings of a file, skipping comments
+fn load_openings(path: &str) -> Vec<Game> {
+    let s =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read {} : {}", path, e));
+    let openings = opening::parse_openings(&s).unwrap_or_else(|e| panic!("{}", e));
+    if openings.is_empty() {
+        panic!("no openings in {}", path);
+    }
+    openings
+}
+
+fn rng_of(seed: Option<u64>) -> rand::rngs::StdRng {
+    match seed {
+        Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+        None => rand::rngs::StdRng::from_entropy(),
+    }
+}
+
+fn generate_openings(count: usize, seed: Option<u64>, options: &OpeningOptions) {
+    let mut rng = rng_of(seed);
+    let mut seen = std::collections::HashSet::new();
+    let mut found = 0;
+    // a narrow range with few plies may not have enough openings
+    let max_attempts = count.saturating_mul(1000);
+    for attempt in 1..=max_attempts {
+        if found == count {
+            break;
+        }
+        if let Some(o) = opening::try_opening(options, &mut rng, &mut seen) {
+            println!("{}", o.to_line());
+            found += 1;
+            eprintln!("{} openings in {} attempts", found, attempt);
+        }
+    }
+    if found < count {
+        eprintln!("only {} openings in {} attempts", found, max_attempts);
+    }
+}
+
+/// Puzzles of the given games, or of self-play games when there are none.
+///
+/// Self-play games start from the openings in turn if there are any.
+fn mine_puzzles(
+    games: Vec<Game>,
+    self_play: usize,
+    strength: &Strength,
+    seed: Option<u64>,
+    openings: &[Game],
+    options: &PuzzleOptions,
+) {
+    let mut rng = rng_of(seed);
+    let games = games
+        .into_iter()
+        .map(Some)
+        .chain((0..self_play).map(|_| None));
+    let mut seen = std::collections::HashSet::new();
+    let mut found = 0;
+    let mut played = 0;
+    for (i, game) in games.enumerate() {
+        let game = game.unwrap_or_else(|| {
+            let mut game = match openings.get(played % openings.len().max(1)) {
+                Some(opening) => opening.clone(),
+                None => transcript::parse("").unwrap(),
+            };
+            played += 1;
+            strength::play_out(&mut game, strength, &mut rng).unwrap_or_else(|e| panic!("{}", e));
+            game
+        });
+        let puzzles =
+            puzzle::find_puzzles(&game, options, &mut seen).unwrap_or_else(|e| panic!("{}", e));
+        for p in &puzzles {
+            println!("{}", p.to_ffo_line());
+        }
+        found += puzzles.len();
+        eprintln!(
+            "game #{} : {} puzzles, {} in total",
+            i + 1,
+            puzzles.len(),
+            found
+        );
+    }
+}
+
+fn write_file(path: &str, content: &str) {
+    std::fs::write(path, content).unwrap_or_else(|e| panic!("failed to write {} : {}", path, e));
+}
+
+fn import_ggf(path: &str) {
+    let s =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read {} : {}", path, e));
+    match ggf::parse_all(&s) {
+        Ok(games) => {
+            for (i, game) in games.iter().enumerate() {
+                println!(
+                    "#{} {} ({:?}) vs {} ({:?}) : {} moves, recorded {:?}, replayed {:+}",
+                    i + 1,
+                    game.black.name,
+                    game.black.rating,
+                    game.white.name,
+                    game.white.rating,
+                    game.moves.len(),
+                    game.score,
+                    game.final_score().unwrap()
+                );
+            }
+        }
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
 
+fn summarize_wthor(path: &str, players: Option<String>, tournaments: Option<String>) {
+    let read_names = |path: Option<String>, record_size| {
+        path.map(|p| {
+            let file =
+                std::fs::File::open(&p).unwrap_or_else(|e| panic!("failed to open {} : {}", p, e));
+            wthor::read_names(std::io::BufReader::new(file), record_size).unwrap()
+        })
+        .unwrap_or_default()
+    };
+    let players = read_names(players, wthor::PLAYER_NAME_SIZE);
+    let tournaments = read_names(tournaments, wthor::TOURNAMENT_NAME_SIZE);
+
+    let reader = wthor::open(path).unwrap();
+    println!("{:?}", reader.header);
+    let (mut games, mut invalid, mut black_wins, mut white_wins, mut total) = (0, 0, 0, 0, 0.0);
+    for record in reader {
+        match record.map(|r| r.to_game(&players, &tournaments)) {
+            Ok(Ok(game)) => {
+                games += 1;
+                let score = game.score.unwrap();
+                total += score;
+                if score > 0.0 {
+                    black_wins += 1;
+                } else if score < 0.0 {
+                    white_wins += 1;
+                }
+            }
+            Ok(Err(e)) => {
+                println!("game #{} : {}", games + invalid + 1, e);
+                invalid += 1;
+            }
+            Err(e) => {
+                println!("{}", e);
+                break;
+            }
+        }
+    }
+    println!(
+        "{} games ({} invalid) : black {} / draw {} / white {}, average score {:+.2}",
+        games,
+        invalid,
+        black_wins,
+        games - black_wins - white_wins,
+        white_wins,
+        total / games.max(1) as f64
+    );
+}
+
+fn run_perft(max_depth: u8, count: impl Fn(u8) -> u64) {
+    println!("| depth |         leaves |  time |");
+    println!("| ----: | -------------: | ----: |");
+    for depth in 1..=max_depth {
+        let started = std::time::Instant::now();
+        let nodes = count(depth);
+        println!(
+            "| {:5} | {:14} | {:4.1}s |",
+            depth,
+            nodes,
+            started.elapsed().as_millis() as f64 / 1000.0
+        );
+    }
+}
+
+fn solve_sized<S: Size>() {
+    let b = Board::<S>::initial();
+    println!("{}", b);
+    let started = std::time::Instant::now();
+    let result = search::complete_search(&b);
+    println!(
+        "best move {}, score {:+}, {} nodes in {}ms",
+        board::square_name_in::<S>(result.idx),
+        result.score,
+        result.stats.nodes,
+        started.elapsed().as_millis()
+    );
+}
+
 /// Benchmark by FFO
 ///
 /// https://github.com/abulmo/edax-reversi/tree/master/problem
 /// https://github.com/primenumber/issen-rs/blob/a77b757662630b0dfe2573fe5ac084659cbb9781/src/main.rs
-fn solve_ffo(start: usize, end: usize) {
+/// Prints like `ConsoleObserver` and keeps the ordering statistics
+#[derive(Default)]
+struct FfoObserver {
+    ordering: std::cell::RefCell<OrderingStats>,
+}
+
+impl search::SearchObserver for FfoObserver {
+    fn iteration(&self, info: &search::SearchInfo) {
+        search::ConsoleObserver.iteration(info);
+        self.ordering.borrow_mut().merge(&info.ordering);
+    }
+}
+
+/// Blank lines and lines starting with `#` are skipped
+fn run_analysis(
+    path: &str,
+    mode: &analyze::Mode,
+    rules: Rules,
+    format: Format,
+    threads: usize,
+    cache: Option<&Mutex<SolvedCache>>,
+) {
+    let input = if path == "-" {
+        let mut s = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut s).unwrap();
+        s
+    } else {
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}", e))
+    };
+    let positions: Vec<(usize, &str)> = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let lines: Vec<&str> = positions.iter().map(|(_, line)| *line).collect();
+    if let Some(header) = analyze::header(format) {
+        println!("{}", header);
+    }
+    analyze::analyze_all(&lines, mode, rules, threads, cache, |i, result| {
+        let (line_no, line) = positions[i];
+        println!("{}", analyze::format_line(format, line_no, line, result));
+    });
+}
+
+fn load_cache(path: &str, max_entries: usize) -> SolvedCache {
+    let cache = SolvedCache::load(path, max_entries).unwrap_or_else(|e| panic!("{}", e));
+    eprintln!("loaded {} solved positions from {}", cache.len(), path);
+    if cache.corrupted > 0 {
+        eprintln!("[WARN] dropped {} corrupted records", cache.corrupted);
+    }
+    cache
+}
+
+fn save_cache(cache: &Option<(Mutex<SolvedCache>, String)>) {
+    if let Some((cache, path)) = cache {
+        let cache = cache.lock().unwrap();
+        cache.save(path).unwrap_or_else(|e| panic!("{}", e));
+        eprintln!("saved {} solved positions to {}", cache.len(), path);
+    }
+}
+
+/// One line per position: board, rules, best move and score
+fn export_cache(path: &str) {
+    let cache = SolvedCache::load(path, usize::MAX).unwrap_or_else(|e| panic!("{}", e));
+    let mut entries: Vec<_> = cache.entries().collect();
+    entries.sort_by_key(|(b, rules, _)| ((b.me | b.opp).count_zeros(), b.me, b.opp, *rules as u8));
+    for (b, rules, solved) in entries {
+        println!(
+            "{} {} {} {:+}",
+            board::serialize(b),
+            format!("{:?}", rules).to_lowercase(),
+            if mobility::get_mobility(b) == 0 {
+                "pass".to_owned()
+            } else {
+                board::square_name(solved.best)
+            },
+            solved.score
+        );
+    }
+    if cache.corrupted > 0 {
+        eprintln!("dropped {} corrupted records", cache.corrupted);
+    }
+}
+
+fn solve_ffo(
+    start: usize,
+    end: usize,
+    ordering: Option<&[OrderingStage]>,
+    cache: Option<&Mutex<SolvedCache>>,
+) {
+    println!(
+        "mobility implementation : {}, flip implementation : {}",
+        mobility::implementation_name(),
+        mobility::flip_implementation_name()
+    );
     let mut results = vec![];
+    let observer = FfoObserver::default();
+    let options = search::SearchOptions {
+        ordering,
+        observer: Some(&observer),
+        cache,
+        ..search::SearchOptions::default()
+    };
     let cases: Vec<(usize, &str)> = FFO_CASES.trim().split('\n').enumerate().collect();
     for (problem, line) in &cases[(start - 1)..=(end - 1)] {
         println!("\n\nFFO#{} {:?}", problem + 1, line);
@@ -73,8 +947,9 @@ fn solve_ffo(start: usize, end: usize) {
         let expected_score: i8 = expected[1].parse().unwrap();
         println!("{}", b);
         let started = std::time::Instant::now();
-        let result = search::complete_search(&b);
+        let result = search::complete_search_with(&b, &options);
         println!("search finished in {}ms", started.elapsed().as_millis());
+        println!("{}", result.stats);
         let passed = result.score == expected_score;
         if !passed {
             println!(
@@ -90,9 +965,9 @@ fn solve_ffo(start: usize, end: usize) {
             result.idx,
             expected_score,
             expected_idx,
-            result.searched as f64 / 1_000_000.0,
+            result.stats.nodes as f64 / 1_000_000.0,
             started.elapsed().as_millis() as f64 / 1000.0,
-            result.searched as f64 / (started.elapsed().as_micros() + 1) as f64,
+            result.stats.nodes as f64 / (started.elapsed().as_micros() + 1) as f64,
         ));
     }
 
@@ -104,8 +979,98 @@ fn solve_ffo(start: usize, end: usize) {
             r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9
         );
     }
+
+    let stats = observer.ordering.borrow();
+    println!(
+        "\nfirst move cutoffs {} / {} cut nodes ({:.1}%)",
+        stats.first_cutoffs,
+        stats.cut_nodes,
+        stats.first_cutoffs as f64 * 100.0 / stats.cut_nodes.max(1) as f64
+    );
+    println!("| stage         | first move | cutoffs |  rate |");
+    println!("| ------------- | ---------: | ------: | ----: |");
+    for stage in ordering::ALL_STAGES {
+        let s = stats.stage(stage);
+        if s.selected > 0 {
+            println!(
+                "| {:13} | {:10} | {:7} | {:4.1}% |",
+                format!("{:?}", stage),
+                s.selected,
+                s.cutoffs,
+                s.first_cutoff_rate() * 100.0
+            );
+        }
+    }
 }
 
+/// Iterative deepening to `depth` with the full window and with aspiration
+/// windows of `window`
+fn bench_midgame(depth: u8, window: i8, ordering: Option<&[OrderingStage]>) {
+    println!(
+        "| No | empties | full nodes |  time | aspiration nodes |  time | re-searches | saved |"
+    );
+    println!(
+        "| --:| ------: | ---------: | ----: | ---------------: | ----: | ----------: | ----: |"
+    );
+    let mut totals = (0, 0);
+    for (i, line) in MIDGAME_CASES.trim().split('\n').enumerate() {
+        let (b, _) = transcript::parse(line)
+            .unwrap()
+            .replay()
+            .unwrap()
+            .pop()
+            .unwrap();
+        let mut runs = vec![];
+        for aspiration in [0, window] {
+            let options = search::SearchOptions {
+                ordering,
+                max_depth: Some(depth),
+                aspiration: Some(aspiration),
+                ..search::SearchOptions::default()
+            };
+            let started = std::time::Instant::now();
+            let stats = search::iterative_search(&b, &options).stats;
+            runs.push((
+                stats.nodes,
+                started.elapsed().as_millis() as f64 / 1000.0,
+                stats.researches,
+            ));
+        }
+        let (full, aspiration) = (runs[0], runs[1]);
+        totals.0 += full.0;
+        totals.1 += aspiration.0;
+        println!(
+            "| {:2} | {:7} | {:>9.2}M | {:4.1}s | {:>15.2}M | {:4.1}s | {:11} | {:4.1}% |",
+            i + 1,
+            (b.me | b.opp).count_zeros(),
+            full.0 as f64 / 1_000_000.0,
+            full.1,
+            aspiration.0 as f64 / 1_000_000.0,
+            aspiration.1,
+            aspiration.2,
+            (1.0 - aspiration.0 as f64 / full.0 as f64) * 100.0
+        );
+    }
+    println!(
+        "\ntotal {:.2}M nodes with the full window, {:.2}M with aspiration windows ({:+.1}%)",
+        totals.0 as f64 / 1_000_000.0,
+        totals.1 as f64 / 1_000_000.0,
+        (totals.1 as f64 / totals.0 as f64 - 1.0) * 100.0
+    );
+}
+
+/// Openings of self-play games at level 2, 20 to 34 plies
+const MIDGAME_CASES: &str = r"
+f5f4e3d2f3g3c3c6e2b4g4f6f7g2d3f2h3h5c5c4
+f5f4d3c4g3d6c7g6h7e2b4f3d2g4h4d1e3g2c3b3c2a4
+f5f6c4g5e6c3d3c2g7e7d2b3b4h8g6c5g8f8b2a2f7f3a3h7
+f5d6c6f4e6c5e3b7f3g4d3g2b5d2h3a4a5e7f8e8f2c4d7c7f1g1
+f5f6f7e3c3e6d3g6g7g8e7b3h6d6h8h5c7e8d8b8c5c8f8b6b5g5d2h7
+f5d6c4d3c6b4d2d1c7b7a4d7c5c3b3f4f3g2e6g3c2c1h1f7b2a1g4b5a3e3
+f5d6c4b3c5c6b5d3e7d7b7c7b4c3d8f7e2g5d2b6f6f8a7a6a4a8e8a5c8e1d1g7
+f5d6c3g5g6d3c5b6h5h7f3g4c4f4a7f2h4c6f7g3e6c7f6d7e7b4e2b7c2d2c8b2g2b5
+";
+
 // https://github.com/abulmo/edax-reversi/tree/master/problem
 const FFO_CASES: &str = r"
 --XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO-- X; G8:+18; H1:+12; H7:+6; A2:+6; A3:+4; B1:-4; A4:-22; G2:-24;
@@ -1,7 +1,89 @@
-use super::board::Board;
+use super::board::{self, Bits, Board, Size, Size8};
+use once_cell::sync::Lazy;
+
+#[cfg(any(test, feature = "flip-table"))]
+mod table;
+#[cfg(target_arch = "x86_64")]
+mod x86;
+
+struct Implementation {
+    name: &'static str,
+    mobility: unsafe fn(&Board) -> u64,
+    #[cfg_attr(feature = "flip-table", allow(dead_code))]
+    flip: unsafe fn(&Board, u64) -> u64,
+}
+
+/// Chosen once by runtime CPU feature detection.
+///
+/// The indirect call costs less than the scalar fallback, see the README.
+static IMPLEMENTATION: Lazy<Implementation> = Lazy::new(|| {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return Implementation {
+                name: "avx2",
+                mobility: x86::get_mobility_avx2,
+                flip: x86::get_flip_avx2,
+            };
+        }
+        if is_x86_feature_detected!("sse2") {
+            return Implementation {
+                name: "sse2",
+                mobility: x86::get_mobility_sse2,
+                flip: x86::get_flip_sse2,
+            };
+        }
+    }
+    Implementation {
+        name: "scalar",
+        mobility: get_mobility_scalar,
+        flip: get_flip_scalar,
+    }
+});
+
+/// Name of the implementation selected for this CPU
+pub fn implementation_name() -> &'static str {
+    IMPLEMENTATION.name
+}
+
+pub fn get_mobility<S: Size>(b: &Board<S>) -> S::Bits {
+    S::mobility(b)
+}
+
+#[inline]
+pub(crate) fn get_mobility_8x8(b: &Board) -> u64 {
+    // SAFETY: the implementation is only selected when the CPU supports it
+    unsafe { (IMPLEMENTATION.mobility)(b) }
+}
+
+/// Name of the flip implementation, which can be replaced at build time
+pub fn flip_implementation_name() -> &'static str {
+    if cfg!(feature = "flip-table") {
+        "table"
+    } else {
+        IMPLEMENTATION.name
+    }
+}
+
+fn get_flip<S: Size>(b: &Board<S>, position: S::Bits) -> S::Bits {
+    S::flip(b, position)
+}
+
+#[cfg(not(feature = "flip-table"))]
+#[inline]
+pub(crate) fn get_flip_8x8(b: &Board, position: u64) -> u64 {
+    // SAFETY: the implementation is only selected when the CPU supports it
+    unsafe { (IMPLEMENTATION.flip)(b, position) }
+}
+
+#[cfg(feature = "flip-table")]
+#[inline]
+pub(crate) fn get_flip_8x8(b: &Board, position: u64) -> u64 {
+    table::get_flip(b, position)
+}
 
 // https://techblog.cccmk.co.jp/entry/2020/12/07/170000
-pub fn get_mobility(b: &Board) -> u64 {
+fn get_mobility_scalar(b: &Board) -> u64 {
     let masks = [
         (1, 0x7e7e7e7e7e7e7e7e), // right & left
         (7, 0x007e7e7e7e7e7e00), // upper-right & lower-left
@@ -27,7 +109,7 @@ pub fn get_mobility(b: &Board) -> u64 {
 }
 
 // https://techblog.cccmk.co.jp/entry/2020/12/07/170000
-fn get_flip(b: &Board, position: u64) -> u64 {
+fn get_flip_scalar(b: &Board, position: u64) -> u64 {
     let masks: [(i32, u64); 4] = [
         (1, 0xfefefefefefefefe),
         (7, 0x7f7f7f7f7f7f7f00),
@@ -54,9 +136,74 @@ fn get_flip(b: &Board, position: u64) -> u64 {
     flip
 }
 
-pub fn put(b: &Board, me_idx: u8) -> Board {
-    let position = 1u64 << me_idx;
-    if (b.me | b.opp) & position != 0 {
+/// `(dx, dy)` of the eight directions
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Every disc moved one square toward `(dx, dy)`, dropping those leaving the board
+#[inline]
+fn shift<S: Size>(x: S::Bits, (dx, dy): (i32, i32)) -> S::Bits {
+    let d = dy * S::N as i32 + dx;
+    let shifted = if d > 0 {
+        x << d as u32
+    } else {
+        x >> (-d) as u32
+    };
+    // a disc leaving a row sideways wraps around to the other edge
+    let mask = match dx {
+        1 => S::FULL & !S::FIRST_COLUMN,
+        -1 => S::FULL & !(S::FIRST_COLUMN << (S::N - 1)),
+        _ => S::FULL,
+    };
+    shifted & mask
+}
+
+/// Mobility by plain shifts, for the sizes without a SIMD implementation
+pub(crate) fn get_mobility_shifts<S: Size>(b: &Board<S>) -> S::Bits {
+    let empty = S::FULL & !(b.me | b.opp);
+    let mut moves = S::Bits::ZERO;
+    for dir in DIRECTIONS {
+        let mut t = shift::<S>(b.me, dir) & b.opp;
+        for _ in 0..S::N - 3 {
+            t |= shift::<S>(t, dir) & b.opp;
+        }
+        moves |= shift::<S>(t, dir) & empty;
+    }
+    moves
+}
+
+/// Flipped discs by plain shifts, for the sizes without a SIMD implementation
+pub(crate) fn get_flip_shifts<S: Size>(b: &Board<S>, position: S::Bits) -> S::Bits {
+    let mut flip = S::Bits::ZERO;
+    for dir in DIRECTIONS {
+        let mut line = S::Bits::ZERO;
+        let mut x = shift::<S>(position, dir);
+        while x & b.opp != S::Bits::ZERO {
+            line |= x;
+            x = shift::<S>(x, dir);
+        }
+        if x & b.me != S::Bits::ZERO {
+            flip |= line;
+        }
+    }
+    flip
+}
+
+/// Play a move without checking it, for the search.
+///
+/// Panics on an occupied square and flips nothing on an illegal one;
+/// use `try_put` for moves from outside.
+pub fn put<S: Size>(b: &Board<S>, me_idx: u8) -> Board<S> {
+    let position = S::Bits::ONE << me_idx as u32;
+    if (b.me | b.opp) & position != S::Bits::ZERO {
         panic!("called put with occupied place : {}\n{}", me_idx, b);
     }
     let flip = get_flip(b, position);
@@ -66,9 +213,141 @@ pub fn put(b: &Board, me_idx: u8) -> Board {
     }
 }
 
+/// Why a move cannot be played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    OutOfBoard(u8),
+    Occupied(u8),
+    /// The square is empty but the move flips nothing
+    NoFlip(u8),
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MoveError::OutOfBoard(idx) => write!(f, "square #{} is out of the board", idx),
+            MoveError::Occupied(idx) => write!(f, "{} is occupied", board::square_name(*idx)),
+            MoveError::NoFlip(idx) => write!(f, "{} flips nothing", board::square_name(*idx)),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Play a move after checking that it is legal
+pub fn try_put(b: &Board, me_idx: u8) -> Result<Board, MoveError> {
+    if me_idx >= 64 {
+        return Err(MoveError::OutOfBoard(me_idx));
+    }
+    let position = 1u64 << me_idx;
+    if (b.me | b.opp) & position != 0 {
+        return Err(MoveError::Occupied(me_idx));
+    }
+    let flip = get_flip(b, position);
+    if flip == 0 {
+        return Err(MoveError::NoFlip(me_idx));
+    }
+    Ok(Board {
+        me: b.opp ^ flip,
+        opp: b.me ^ (position | flip),
+    })
+}
+
+/// Legal moves of the side to move, in square order
+pub fn legal_moves<S: Size>(b: &Board<S>) -> LegalMoves<S> {
+    LegalMoves(get_mobility(b))
+}
+
+/// Iterator over the squares of a mobility bitboard
+#[derive(Debug, Clone)]
+pub struct LegalMoves<S: Size = Size8>(S::Bits);
+
+impl<S: Size> Iterator for LegalMoves<S> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == S::Bits::ZERO {
+            return None;
+        }
+        let idx = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - S::Bits::ONE;
+        Some(idx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl<S: Size> ExactSizeIterator for LegalMoves<S> {}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{Rng, SeedableRng};
+
+    /// Random disjoint bitboards, not necessarily reachable by play
+    fn random_boards(n: usize) -> Vec<Board> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        (0..n)
+            .map(|_| {
+                let occupied: u64 = rng.gen::<u64>() | rng.gen::<u64>();
+                let me = occupied & rng.gen::<u64>();
+                Board {
+                    me,
+                    opp: occupied & !me,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_implementations_match() {
+        println!("selected : {}", implementation_name());
+        let mut candidates: Vec<Implementation> = vec![];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                candidates.push(Implementation {
+                    name: "avx2",
+                    mobility: x86::get_mobility_avx2,
+                    flip: x86::get_flip_avx2,
+                });
+            }
+            candidates.push(Implementation {
+                name: "sse2",
+                mobility: x86::get_mobility_sse2,
+                flip: x86::get_flip_sse2,
+            });
+        }
+        // the generic code of the other sizes
+        candidates.push(Implementation {
+            name: "shifts",
+            mobility: get_mobility_shifts::<Size8>,
+            flip: get_flip_shifts::<Size8>,
+        });
+        for b in random_boards(10000) {
+            let mobility = get_mobility_scalar(&b);
+            let empty = !(b.me | b.opp);
+            for c in &candidates {
+                // SAFETY: avx2 is checked above, sse2 is part of x86_64 and the
+                // shifts are safe
+                unsafe {
+                    assert_eq!((c.mobility)(&b), mobility, "{} {:?}", c.name, b);
+                    for idx in 0..64 {
+                        let position = 1u64 << idx;
+                        if empty & position == 0 {
+                            continue;
+                        }
+                        let flip = get_flip_scalar(&b, position);
+                        assert_eq!(table::get_flip(&b, position), flip, "table {:?} {}", b, idx);
+                        assert_eq!((c.flip)(&b, position), flip, "{} {:?} {}", c.name, b, idx);
+                    }
+                }
+            }
+        }
+    }
 
     #[test]
     fn test_mobility() {
@@ -80,12 +359,24 @@ mod test {
             (35253091565568, 172201869312, 292595957760),
         ];
         for (me, opp, m) in cases {
-            let b = Board { me, opp };
+            let b: Board = Board { me, opp };
             println!("{}", b);
             assert_eq!(get_mobility(&b), m);
         }
     }
 
+    #[test]
+    fn test_try_put() {
+        let b = Board::new();
+        assert_eq!(legal_moves(&b).collect::<Vec<u8>>(), vec![29, 43, 45]);
+        for idx in legal_moves(&b) {
+            assert_eq!(try_put(&b, idx), Ok(put(&b, idx)));
+        }
+        assert_eq!(try_put(&b, 37), Err(MoveError::Occupied(37)));
+        assert_eq!(try_put(&b, 0), Err(MoveError::NoFlip(0)));
+        assert_eq!(try_put(&b, 64), Err(MoveError::OutOfBoard(64)));
+    }
+
     #[test]
     fn test_flip() {
         #[rustfmt::skip]
@@ -113,7 +404,7 @@ mod test {
             (71308411146753, 2319419934326385918, 32, 2319419934292831486, 71312739668481),
         ];
         for (me, opp, idx, next_me, next_opp) in cases {
-            let b = Board { me, opp };
+            let b: Board = Board { me, opp };
             let actual = put(&b, idx);
             let expected = Board {
                 me: next_me,
@@ -0,0 +1,148 @@
+//! Flip computation by per-line lookup tables (kindergarten bitboards).
+//!
+//! Each of the 4 lines through the position is packed into 8 bits,
+//! the outflanking discs and the flipped discs are looked up,
+//! and the result is unpacked back onto the board.
+//! https://github.com/abulmo/edax-reversi/blob/master/src/flip_kindergarten.c
+
+use super::super::board::Board;
+
+/// `OUTFLANK[x][inner]` is the first square which is not an opponent disc
+/// on each side of `x`, where `inner` holds the opponent discs on squares 1..=6.
+static OUTFLANK: [[u8; 64]; 8] = outflank_table();
+
+/// `FLIPPED[x][outflank]` is the squares between `x` and the outflanking discs.
+static FLIPPED: [[u8; 256]; 8] = flipped_table();
+
+/// Unpack 8 bits into the A column
+static COLUMN: [u64; 256] = column_table();
+
+static DIAGONAL: [u64; 64] = diagonal_masks(9);
+static ANTI_DIAGONAL: [u64; 64] = diagonal_masks(7);
+
+const fn outflank_table() -> [[u8; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut x = 0;
+    while x < 8 {
+        let mut inner = 0;
+        while inner < 64 {
+            let opp = (inner << 1) as u8;
+            let mut outflank = 0u8;
+            if x > 0 {
+                let mut i = x - 1;
+                while i > 0 && opp >> i & 1 == 1 {
+                    i -= 1;
+                }
+                outflank |= 1 << i;
+            }
+            if x < 7 {
+                let mut i = x + 1;
+                while i < 7 && opp >> i & 1 == 1 {
+                    i += 1;
+                }
+                outflank |= 1 << i;
+            }
+            table[x][inner] = outflank;
+            inner += 1;
+        }
+        x += 1;
+    }
+    table
+}
+
+const fn flipped_table() -> [[u8; 256]; 8] {
+    let mut table = [[0; 256]; 8];
+    let mut x = 0;
+    while x < 8 {
+        let mut outflank = 0;
+        while outflank < 256 {
+            let mut flipped = 0u8;
+            let mut i = 0;
+            while i < 8 {
+                if outflank >> i & 1 == 1 {
+                    let (from, to) = if i < x { (i + 1, x) } else { (x + 1, i) };
+                    let mut j = from;
+                    while j < to {
+                        flipped |= 1 << j;
+                        j += 1;
+                    }
+                }
+                i += 1;
+            }
+            table[x][outflank] = flipped;
+            outflank += 1;
+        }
+        x += 1;
+    }
+    table
+}
+
+const fn column_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut bits = 0;
+    while bits < 256 {
+        let mut y = 0;
+        while y < 8 {
+            if bits >> y & 1 == 1 {
+                table[bits] |= 1 << (y * 8);
+            }
+            y += 1;
+        }
+        bits += 1;
+    }
+    table
+}
+
+/// Squares on the line through each square, stepping by `step` (9 or 7)
+const fn diagonal_masks(step: i32) -> [u64; 64] {
+    let dx = if step == 9 { 1 } else { -1 };
+    let mut masks = [0; 64];
+    let mut idx = 0;
+    while idx < 64 {
+        let (x0, y0) = ((idx % 8) as i32, (idx / 8) as i32);
+        let mut k = -7;
+        while k <= 7 {
+            let (x, y) = (x0 + k * dx, y0 + k);
+            if 0 <= x && x < 8 && 0 <= y && y < 8 {
+                masks[idx] |= 1 << (y * 8 + x);
+            }
+            k += 1;
+        }
+        idx += 1;
+    }
+    masks
+}
+
+#[inline]
+fn flip_line(x: usize, me: u64, opp: u64) -> u64 {
+    let outflank = OUTFLANK[x][(opp >> 1) as usize & 0x3f] & me as u8;
+    FLIPPED[x][outflank as usize] as u64
+}
+
+#[inline]
+fn pack_column(bits: u64) -> u64 {
+    (bits & 0x0101010101010101).wrapping_mul(0x0102040810204080) >> 56
+}
+
+#[inline]
+fn pack_diagonal(bits: u64, mask: u64) -> u64 {
+    (bits & mask).wrapping_mul(0x0101010101010101) >> 56
+}
+
+/// Diagonals are indexed by the column, as they have one square per column.
+#[inline]
+fn flip_diagonal(b: &Board, x: usize, mask: u64) -> u64 {
+    let flipped = flip_line(x, pack_diagonal(b.me, mask), pack_diagonal(b.opp, mask));
+    flipped.wrapping_mul(0x0101010101010101) & mask
+}
+
+pub fn get_flip(b: &Board, position: u64) -> u64 {
+    let idx = position.trailing_zeros() as usize;
+    let (x, y) = (idx % 8, idx / 8);
+
+    let row = flip_line(x, b.me >> (y * 8), b.opp >> (y * 8)) << (y * 8);
+    let column =
+        COLUMN[flip_line(y, pack_column(b.me >> x), pack_column(b.opp >> x)) as usize] << x;
+
+    row | column | flip_diagonal(b, x, DIAGONAL[idx]) | flip_diagonal(b, x, ANTI_DIAGONAL[idx])
+}
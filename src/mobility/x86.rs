@@ -0,0 +1,161 @@
+use std::arch::x86_64::*;
+
+use super::super::board::Board;
+
+const SHIFTS: [i64; 4] = [1, 7, 8, 9];
+
+// masks of the squares which an opponent disc may occupy inside a line
+const MOBILITY_MASKS: [u64; 4] = [
+    0x7e7e7e7e7e7e7e7e,
+    0x007e7e7e7e7e7e00,
+    0x00ffffffffffff00,
+    0x007e7e7e7e7e7e00,
+];
+
+// masks of the squares reachable by a left shift without wrapping around
+const LEFT_MASKS: [u64; 4] = [
+    0xfefefefefefefefe,
+    0x7f7f7f7f7f7f7f00,
+    0xffffffffffffff00,
+    0xfefefefefefefe00,
+];
+
+// same as LEFT_MASKS for a right shift
+const RIGHT_MASKS: [u64; 4] = [
+    0x7f7f7f7f7f7f7f7f,
+    0x00fefefefefefefe,
+    0x00ffffffffffffff,
+    0x007f7f7f7f7f7f7f,
+];
+
+#[inline]
+unsafe fn load(v: &[u64; 4]) -> __m256i {
+    _mm256_loadu_si256(v.as_ptr() as *const __m256i)
+}
+
+#[inline]
+unsafe fn or_lanes(v: __m256i) -> u64 {
+    let mut lanes = [0u64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, v);
+    lanes[0] | lanes[1] | lanes[2] | lanes[3]
+}
+
+/// Four directions at once, one per 64-bit lane.
+///
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub unsafe fn get_mobility_avx2(b: &Board) -> u64 {
+    let shifts = load(&SHIFTS.map(|s| s as u64));
+    let me = _mm256_set1_epi64x(b.me as i64);
+    let mask = _mm256_and_si256(load(&MOBILITY_MASKS), _mm256_set1_epi64x(b.opp as i64));
+
+    let mut l = _mm256_and_si256(mask, _mm256_sllv_epi64(me, shifts));
+    let mut r = _mm256_and_si256(mask, _mm256_srlv_epi64(me, shifts));
+    for _ in 0..5 {
+        l = _mm256_or_si256(l, _mm256_and_si256(mask, _mm256_sllv_epi64(l, shifts)));
+        r = _mm256_or_si256(r, _mm256_and_si256(mask, _mm256_srlv_epi64(r, shifts)));
+    }
+    let candidate = _mm256_or_si256(_mm256_sllv_epi64(l, shifts), _mm256_srlv_epi64(r, shifts));
+
+    or_lanes(candidate) & !(b.me | b.opp)
+}
+
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub unsafe fn get_flip_avx2(b: &Board, position: u64) -> u64 {
+    let shifts = load(&SHIFTS.map(|s| s as u64));
+    let pos = _mm256_set1_epi64x(position as i64);
+    let me = _mm256_set1_epi64x(b.me as i64);
+    let opp = _mm256_set1_epi64x(b.opp as i64);
+    let l_mask = load(&LEFT_MASKS);
+    let r_mask = load(&RIGHT_MASKS);
+    let l_opp = _mm256_and_si256(l_mask, opp);
+    let r_opp = _mm256_and_si256(r_mask, opp);
+
+    let mut l = _mm256_and_si256(l_opp, _mm256_sllv_epi64(pos, shifts));
+    let mut r = _mm256_and_si256(r_opp, _mm256_srlv_epi64(pos, shifts));
+    for _ in 0..5 {
+        l = _mm256_or_si256(l, _mm256_and_si256(l_opp, _mm256_sllv_epi64(l, shifts)));
+        r = _mm256_or_si256(r, _mm256_and_si256(r_opp, _mm256_srlv_epi64(r, shifts)));
+    }
+    let l_outflank = _mm256_and_si256(_mm256_and_si256(l_mask, me), _mm256_sllv_epi64(l, shifts));
+    let r_outflank = _mm256_and_si256(_mm256_and_si256(r_mask, me), _mm256_srlv_epi64(r, shifts));
+
+    // keep only the lines closed by one of my discs
+    let zero = _mm256_setzero_si256();
+    let l = _mm256_andnot_si256(_mm256_cmpeq_epi64(l_outflank, zero), l);
+    let r = _mm256_andnot_si256(_mm256_cmpeq_epi64(r_outflank, zero), r);
+
+    or_lanes(_mm256_or_si256(l, r))
+}
+
+/// Two lanes holding the board and its 180 degree rotation,
+/// so every right shift becomes a left shift with the same mask.
+///
+/// # Safety
+///
+/// The CPU must support SSE2.
+#[target_feature(enable = "sse2")]
+pub unsafe fn get_mobility_sse2(b: &Board) -> u64 {
+    let me = _mm_set_epi64x(b.me.reverse_bits() as i64, b.me as i64);
+    let opp = _mm_set_epi64x(b.opp.reverse_bits() as i64, b.opp as i64);
+    let mut candidate = _mm_setzero_si128();
+
+    for (shift, mask) in SHIFTS.iter().zip(MOBILITY_MASKS.iter()) {
+        let shift = _mm_cvtsi64_si128(*shift);
+        let mask = _mm_and_si128(_mm_set1_epi64x(*mask as i64), opp);
+        let mut bits = _mm_and_si128(mask, _mm_sll_epi64(me, shift));
+        for _ in 0..5 {
+            bits = _mm_or_si128(bits, _mm_and_si128(mask, _mm_sll_epi64(bits, shift)));
+        }
+        candidate = _mm_or_si128(candidate, _mm_sll_epi64(bits, shift));
+    }
+
+    let (l, r) = split(candidate);
+    (l | r.reverse_bits()) & !(b.me | b.opp)
+}
+
+/// # Safety
+///
+/// The CPU must support SSE2.
+#[target_feature(enable = "sse2")]
+pub unsafe fn get_flip_sse2(b: &Board, position: u64) -> u64 {
+    let pos = _mm_set_epi64x(position.reverse_bits() as i64, position as i64);
+    let me = _mm_set_epi64x(b.me.reverse_bits() as i64, b.me as i64);
+    let opp = _mm_set_epi64x(b.opp.reverse_bits() as i64, b.opp as i64);
+    let mut l_flip = 0;
+    let mut r_flip = 0;
+
+    for (shift, mask) in SHIFTS.iter().zip(LEFT_MASKS.iter()) {
+        let shift = _mm_cvtsi64_si128(*shift);
+        let mask = _mm_set1_epi64x(*mask as i64);
+        let mask_opp = _mm_and_si128(mask, opp);
+        let mut bits = _mm_and_si128(mask_opp, _mm_sll_epi64(pos, shift));
+        for _ in 0..5 {
+            bits = _mm_or_si128(bits, _mm_and_si128(mask_opp, _mm_sll_epi64(bits, shift)));
+        }
+        let outflank = _mm_and_si128(_mm_and_si128(mask, me), _mm_sll_epi64(bits, shift));
+
+        let (l, r) = split(bits);
+        let (l_outflank, r_outflank) = split(outflank);
+        if l_outflank != 0 {
+            l_flip |= l;
+        }
+        if r_outflank != 0 {
+            r_flip |= r;
+        }
+    }
+    l_flip | r_flip.reverse_bits()
+}
+
+#[inline]
+unsafe fn split(v: __m128i) -> (u64, u64) {
+    (
+        _mm_cvtsi128_si64(v) as u64,
+        _mm_cvtsi128_si64(_mm_unpackhi_epi64(v, v)) as u64,
+    )
+}
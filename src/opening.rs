@@ -0,0 +1,194 @@
+//! Balanced random openings, as in XOT
+//!
+//! Random legal moves are played from the initial position and the resulting
+//! positions are kept when a search scores them close to even. Openings are
+//! written one transcript per line with the score as a comment, e.g.
+//! `f5d6c3d3c4f4f6f3 # +1`, and the rules after the transcript unless they
+//! are standard, e.g. `e6f6c4e3e2c3f5d6 anti # -1`.
+
+use super::board::Board;
+use super::game::{Color, Game, Move, Rules};
+use super::mobility;
+use super::search::{self, TimeLimit};
+use super::transcript::{self, TranscriptError};
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningOptions {
+    /// Random moves from the initial position
+    pub plies: usize,
+    /// Depth of the search scoring the position
+    pub depth: u8,
+    /// Positions scored within `-max_score..=max_score` are kept
+    pub max_score: i8,
+    pub rules: Rules,
+}
+
+impl Default for OpeningOptions {
+    fn default() -> OpeningOptions {
+        OpeningOptions {
+            plies: 8,
+            depth: 10,
+            max_score: 2,
+            rules: Rules::Standard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opening {
+    pub game: Game,
+    /// From Black's point of view
+    pub score: i8,
+}
+
+impl Opening {
+    pub fn to_line(&self) -> String {
+        let rules = match self.game.rules {
+            Rules::Standard => "",
+            Rules::Anti => " anti",
+        };
+        format!(
+            "{}{} # {:+}",
+            transcript::serialize(&self.game, false),
+            rules,
+            self.score
+        )
+    }
+}
+
+/// `plies` random legal moves, None if the game ends before
+pub fn random_game<R: Rng>(plies: usize, rng: &mut R) -> Option<Game> {
+    let mut game = Game::new(Board::initial(), Color::Black);
+    let mut b = game.start.clone();
+    let mut color = game.start_color;
+    while game.moves.len() < plies {
+        let moves: Vec<u8> = mobility::legal_moves(&b).collect();
+        let idx = if !moves.is_empty() {
+            Some(moves[rng.gen_range(0..moves.len())])
+        } else if mobility::get_mobility(&b.pass()) != 0 {
+            None
+        } else {
+            return None;
+        };
+        game.moves.push(Move::new(color, idx));
+        b = match idx {
+            Some(idx) => mobility::put(&b, idx),
+            None => b.pass(),
+        };
+        color = color.opposite();
+    }
+    Some(game)
+}
+
+/// Score of the best move to `depth`, from Black's point of view.
+///
+/// None if the side to move has to pass, which no opening should start with.
+pub fn evaluate(game: &Game, depth: u8) -> Option<i8> {
+    let (b, color) = game.replay().ok()?.pop().unwrap();
+    // the depth bounds the search, not the time
+    let unlimited = TimeLimit {
+        soft: Duration::MAX,
+        hard: Duration::MAX,
+    };
+    let score = search::score_moves(&b, game.rules, depth, &unlimited)
+        .into_iter()
+        .map(|(_, score)| score)
+        .max()?;
+    Some(match color {
+        Color::Black => score,
+        Color::White => -score,
+    })
+}
+
+/// A random balanced opening, None if the attempt failed.
+///
+/// Positions in `seen`, by their canonical board, are rejected, and the
+/// scored ones are added.
+pub fn try_opening<R: Rng>(
+    options: &OpeningOptions,
+    rng: &mut R,
+    seen: &mut HashSet<Board>,
+) -> Option<Opening> {
+    let mut game = random_game(options.plies, rng)?;
+    game.rules = options.rules;
+    let (b, _) = game.replay().unwrap().pop().unwrap();
+    if !seen.insert(b.canonical()) {
+        return None;
+    }
+    let score = evaluate(&game, options.depth)?;
+    (score.abs() <= options.max_score).then_some(Opening { game, score })
+}
+
+/// Openings of a file written by `Opening::to_line`, or any transcripts.
+///
+/// Blank lines and comments after `#` are skipped.
+pub fn parse_openings(s: &str) -> Result<Vec<Game>, TranscriptError> {
+    s.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+/// A transcript optionally followed by the rules
+fn parse_line(line: &str) -> Result<Game, TranscriptError> {
+    let (moves, rules) = match line.rsplit_once(char::is_whitespace) {
+        Some((moves, rules)) => match rules.parse() {
+            Ok(rules) => (moves, rules),
+            Err(_) => (line, Rules::Standard),
+        },
+        None => (line, Rules::Standard),
+    };
+    let mut game = transcript::parse(moves)?;
+    game.rules = rules;
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_try_opening() {
+        let options = OpeningOptions {
+            plies: 6,
+            depth: 4,
+            ..OpeningOptions::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut seen = HashSet::new();
+        let openings: Vec<Opening> = (0..200)
+            .filter_map(|_| try_opening(&options, &mut rng, &mut seen))
+            .collect();
+        assert!(openings.len() > 1);
+
+        let lines: Vec<String> = openings.iter().map(|o| o.to_line()).collect();
+        let games = parse_openings(&format!("# openings\n\n{}\n", lines.join("\n"))).unwrap();
+        assert_eq!(games.len(), openings.len());
+        let mut canonical = HashSet::new();
+        for (opening, game) in openings.iter().zip(&games) {
+            assert!(opening.score.abs() <= options.max_score);
+            assert_eq!(game.moves.len(), 6);
+            assert_eq!(game.rules, Rules::Standard);
+            assert_eq!(evaluate(game, options.depth), Some(opening.score));
+            let (b, _) = game.replay().unwrap().pop().unwrap();
+            assert!(canonical.insert(b.canonical()));
+        }
+
+        let anti = OpeningOptions {
+            rules: Rules::Anti,
+            ..options
+        };
+        let opening = (0..200)
+            .find_map(|_| try_opening(&anti, &mut rng, &mut HashSet::new()))
+            .unwrap();
+        let line = opening.to_line();
+        assert!(line.contains(" anti # "));
+        let games = parse_openings(&line).unwrap();
+        assert_eq!(games, vec![opening.game]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Move ordering as a list of stages
+//!
+//! Each stage gives every move a key, smaller first. Moves are sorted by the
+//! keys of the stages in order, so a later stage only breaks ties of the
+//! earlier ones.
+
+use super::board::MAX_SQUARES;
+
+/// Number of kinds of stages
+pub const STAGES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderingStage {
+    /// The best move stored in the transposition table
+    TtMove,
+    /// Moves which caused a beta cut at the same ply
+    Killers,
+    /// Moves which caused beta cuts anywhere, weighted by depth
+    History,
+    /// Scores of a shallow search of each move
+    ShallowSearch,
+    /// Fewest replies of the opponent
+    FastestFirst,
+}
+
+pub const ALL_STAGES: [OrderingStage; STAGES] = [
+    OrderingStage::TtMove,
+    OrderingStage::Killers,
+    OrderingStage::History,
+    OrderingStage::ShallowSearch,
+    OrderingStage::FastestFirst,
+];
+
+/// Killers, history and shallow search did not reduce nodes further,
+/// neither in the midgame nor on FFO problems.
+pub const DEFAULT_ORDERING: &[OrderingStage] =
+    &[OrderingStage::TtMove, OrderingStage::FastestFirst];
+
+impl std::str::FromStr for OrderingStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OrderingStage, String> {
+        match s {
+            "tt" => Ok(OrderingStage::TtMove),
+            "killers" => Ok(OrderingStage::Killers),
+            "history" => Ok(OrderingStage::History),
+            "shallow" => Ok(OrderingStage::ShallowSearch),
+            "fastest" => Ok(OrderingStage::FastestFirst),
+            _ => Err(format!("unknown ordering stage {}", s)),
+        }
+    }
+}
+
+/// Parse a comma separated list such as `tt,killers,fastest`
+pub fn parse(s: &str) -> Result<Vec<OrderingStage>, String> {
+    let stages: Vec<OrderingStage> = s
+        .split(',')
+        .map(|stage| stage.trim().parse())
+        .collect::<Result<_, _>>()?;
+    if stages.len() > STAGES {
+        return Err(format!("too many ordering stages {}", s));
+    }
+    Ok(stages)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageStats {
+    /// Nodes where this stage decided which move is searched first
+    pub selected: u64,
+    /// Of those, the first move caused a beta cut
+    pub cutoffs: u64,
+}
+
+impl StageStats {
+    pub fn first_cutoff_rate(&self) -> f64 {
+        self.cutoffs as f64 / self.selected.max(1) as f64
+    }
+}
+
+/// How well the ordering predicts the move causing a beta cut
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderingStats {
+    /// Ordered nodes with a beta cut
+    pub cut_nodes: u64,
+    /// Of those, the first move caused the cut
+    pub first_cutoffs: u64,
+    /// Indexed by `OrderingStage as usize`
+    pub stages: [StageStats; STAGES],
+}
+
+impl OrderingStats {
+    pub fn merge(&mut self, other: &OrderingStats) {
+        self.cut_nodes += other.cut_nodes;
+        self.first_cutoffs += other.first_cutoffs;
+        for (s, o) in self.stages.iter_mut().zip(&other.stages) {
+            s.selected += o.selected;
+            s.cutoffs += o.cutoffs;
+        }
+    }
+
+    pub fn stage(&self, stage: OrderingStage) -> &StageStats {
+        &self.stages[stage as usize]
+    }
+}
+
+/// Two moves per ply which recently caused beta cuts
+pub(crate) struct Killers([[u8; 2]; MAX_SQUARES]);
+
+impl Killers {
+    pub(crate) fn new() -> Killers {
+        Killers([[u8::MAX; 2]; MAX_SQUARES])
+    }
+
+    pub(crate) fn add(&mut self, ply: u8, idx: u8) {
+        let slots = &mut self.0[ply as usize];
+        if slots[0] != idx {
+            slots[1] = slots[0];
+            slots[0] = idx;
+        }
+    }
+
+    /// 0 or 1 for a killer, 2 otherwise
+    pub(crate) fn rank(&self, ply: u8, idx: u8) -> i32 {
+        let slots = &self.0[ply as usize];
+        if slots[0] == idx {
+            0
+        } else if slots[1] == idx {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// Beta cut counts per square, separately for each parity of the ply
+pub(crate) struct History([[u32; MAX_SQUARES]; 2]);
+
+impl History {
+    pub(crate) fn new() -> History {
+        History([[0; MAX_SQUARES]; 2])
+    }
+
+    pub(crate) fn add(&mut self, ply: u8, idx: u8, remaining: u8) {
+        let h = &mut self.0[ply as usize & 1][idx as usize];
+        *h = h.saturating_add(remaining as u32 * remaining as u32);
+    }
+
+    pub(crate) fn get(&self, ply: u8, idx: u8) -> u32 {
+        self.0[ply as usize & 1][idx as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("tt,fastest"), Ok(DEFAULT_ORDERING.to_vec()));
+        assert_eq!(
+            parse("tt,killers,history,shallow,fastest"),
+            Ok(ALL_STAGES.to_vec())
+        );
+        assert!(parse("tt,nothing").is_err());
+
+        let mut killers = Killers::new();
+        killers.add(3, 10);
+        killers.add(3, 20);
+        killers.add(3, 20);
+        assert_eq!(
+            (
+                killers.rank(3, 20),
+                killers.rank(3, 10),
+                killers.rank(4, 20)
+            ),
+            (0, 1, 2)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::board::{Bits, Board, Size};
+use super::mobility;
+use std::collections::HashMap;
+
+/// Count leaf nodes of the game tree to the given depth.
+///
+/// A pass consumes one ply, and a finished game counts as a single leaf
+/// regardless of the remaining depth.
+/// https://www.aartbik.com/MISC/reversi.html
+pub fn perft<S: Size>(b: &Board<S>, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut mobility = mobility::get_mobility(b);
+    if mobility == S::Bits::ZERO {
+        let passed = b.pass();
+        if mobility::get_mobility(&passed) == S::Bits::ZERO {
+            return 1;
+        }
+        return perft(&passed, depth - 1);
+    }
+    if depth == 1 {
+        return mobility.count_ones() as u64;
+    }
+    let mut nodes = 0;
+    while mobility != S::Bits::ZERO {
+        let idx = mobility.trailing_zeros() as u8;
+        mobility &= mobility - S::Bits::ONE;
+        nodes += perft(&mobility::put(b, idx), depth - 1);
+    }
+    nodes
+}
+
+/// Same as `perft`, but caches subtree counts keyed by the canonical board.
+///
+/// Positions reached by transposition or symmetry are counted only once,
+/// which makes depths beyond 10 practical.
+pub fn perft_hashed(b: &Board, depth: u8) -> u64 {
+    let mut cache = HashMap::new();
+    rec_perft_hashed(b, depth, &mut cache)
+}
+
+fn rec_perft_hashed(b: &Board, depth: u8, cache: &mut HashMap<(Board, u8), u64>) -> u64 {
+    if depth <= 2 {
+        return perft(b, depth);
+    }
+    let key = (b.canonical(), depth);
+    if let Some(nodes) = cache.get(&key) {
+        return *nodes;
+    }
+    let mut mobility = mobility::get_mobility(b);
+    let nodes = if mobility == 0 {
+        let passed = b.pass();
+        if mobility::get_mobility(&passed) == 0 {
+            1
+        } else {
+            rec_perft_hashed(&passed, depth - 1, cache)
+        }
+    } else {
+        let mut nodes = 0;
+        while mobility != 0 {
+            let idx = mobility.trailing_zeros() as u8;
+            mobility ^= 1 << idx;
+            nodes += rec_perft_hashed(&mobility::put(b, idx), depth - 1, cache);
+        }
+        nodes
+    };
+    cache.insert(key, nodes);
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{self, Size10, Size6};
+
+    // https://oeis.org/A124004
+    const EXPECTED: [u64; 11] = [
+        1, 4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288, 24571284,
+    ];
+
+    #[test]
+    fn test_perft() {
+        let b: Board = Board::initial();
+        for (depth, expected) in EXPECTED.iter().enumerate().take(9) {
+            assert_eq!(perft(&b, depth as u8), *expected, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn test_perft_sizes() {
+        // the edges stop the lines sooner on 6x6 and later on 10x10
+        let b = Board::<Size6>::initial();
+        for (depth, expected) in EXPECTED.iter().enumerate().take(5) {
+            assert_eq!(perft(&b, depth as u8), *expected, "depth {}", depth);
+        }
+        assert!(perft(&b, 5) < EXPECTED[5]);
+        let b = Board::<Size10>::initial();
+        for (depth, expected) in EXPECTED.iter().enumerate().take(7) {
+            assert_eq!(perft(&b, depth as u8), *expected, "depth {}", depth);
+        }
+        assert!(perft(&b, 7) > EXPECTED[7]);
+
+        assert_eq!(
+            mobility::legal_moves(&b).collect::<Vec<_>>(),
+            vec![34, 43, 56, 65]
+        );
+        assert_eq!(board::square_name_in::<Size10>(34), "e4");
+        let b = mobility::put(&b, 34);
+        assert_eq!((b.me.count_ones(), b.opp.count_ones()), (1, 4));
+    }
+
+    #[test]
+    fn test_perft_hashed() {
+        let b = Board::initial();
+        for (depth, expected) in EXPECTED.iter().enumerate() {
+            assert_eq!(perft_hashed(&b, depth as u8), *expected, "depth {}", depth);
+        }
+    }
+}
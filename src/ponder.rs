@@ -0,0 +1,186 @@
+//! Searching on the opponent's time
+//!
+//! Results are written to a shared `SearchState`,
+//! so the search after the actual reply resumes from them.
+
+use super::board::Board;
+use super::game::Rules;
+use super::mobility;
+use super::search::{self, SearchOptions, SearchState, TimeLimit};
+use super::tt::TranspositionTable;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PonderMode {
+    /// Only the reply found by a short search
+    Predicted,
+    /// Every reply, deepened in turn
+    All,
+}
+
+impl std::str::FromStr for PonderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PonderMode, String> {
+        match s {
+            "predicted" => Ok(PonderMode::Predicted),
+            "all" => Ok(PonderMode::All),
+            _ => Err(format!("unknown ponder mode {}", s)),
+        }
+    }
+}
+
+/// Time given to each reply in the first round of `PonderMode::All`
+const ROUND_MS: u64 = 100;
+
+/// A running background search, stopped when dropped
+pub struct Ponder {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Start pondering on `b`, where the opponent is to move.
+///
+/// `tt` is locked by each search in turn, so it is free once stopped.
+pub fn start(
+    b: &Board,
+    mode: PonderMode,
+    rules: Rules,
+    state: Arc<Mutex<SearchState>>,
+    tt: Arc<Mutex<TranspositionTable>>,
+) -> Ponder {
+    let stop = Arc::new(AtomicBool::new(false));
+    let b = b.clone();
+    let handle = {
+        let stop = stop.clone();
+        std::thread::spawn(move || run(&b, mode, rules, &state, &tt, &stop))
+    };
+    Ponder {
+        stop,
+        handle: Some(handle),
+    }
+}
+
+/// Positions after each reply which is worth pondering
+fn candidates(
+    b: &Board,
+    mode: PonderMode,
+    rules: Rules,
+    tt: &Mutex<TranspositionTable>,
+    stop: &AtomicBool,
+) -> Vec<Board> {
+    let mobility = mobility::get_mobility(b);
+    if mobility == 0 {
+        // the opponent passes
+        return vec![b.pass()];
+    }
+    match mode {
+        PonderMode::Predicted => {
+            let options = SearchOptions {
+                limit: Some(TimeLimit::fixed(ROUND_MS)),
+                stop: Some(stop),
+                rules,
+                tt: Some(tt),
+                ..SearchOptions::default()
+            };
+            let reply = search::iterative_search(b, &options).idx;
+            vec![mobility::put(b, reply)]
+        }
+        PonderMode::All => mobility::legal_moves(b)
+            .map(|idx| mobility::put(b, idx))
+            .collect(),
+    }
+}
+
+fn run(
+    b: &Board,
+    mode: PonderMode,
+    rules: Rules,
+    state: &Mutex<SearchState>,
+    tt: &Mutex<TranspositionTable>,
+    stop: &AtomicBool,
+) {
+    let mut positions = candidates(b, mode, rules, tt, stop);
+    let mut ms = ROUND_MS;
+    while !positions.is_empty() && !stop.load(Ordering::Relaxed) {
+        // a single position is searched until stopped
+        let limit = if positions.len() == 1 {
+            None
+        } else {
+            Some(TimeLimit::fixed(ms))
+        };
+        for p in &positions {
+            let options = SearchOptions {
+                limit: limit.clone(),
+                stop: Some(stop),
+                state: Some(state),
+                rules,
+                tt: Some(tt),
+                ..SearchOptions::default()
+            };
+            search::iterative_search(p, &options);
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+        // positions which are solved or which have no move need no more work
+        let state = state.lock().unwrap();
+        positions.retain(|p| {
+            mobility::get_mobility(p) != 0 && !matches!(state.get(p), Some((_, r)) if r.game_end)
+        });
+        if limit.is_none() {
+            break;
+        }
+        ms *= 2;
+    }
+}
+
+impl Ponder {
+    /// Stop the background search and wait for it
+    pub fn stop(self) {}
+}
+
+impl Drop for Ponder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tt;
+
+    #[test]
+    fn test_ponder() {
+        let b = Board::new();
+        let state = Arc::new(Mutex::new(SearchState::default()));
+        let tt = Arc::new(Mutex::new(TranspositionTable::new(tt::DEFAULT_BITS)));
+        let mut ponder = start(&b, PonderMode::All, Rules::Standard, state.clone(), tt);
+        let replies: Vec<Board> = mobility::legal_moves(&b)
+            .map(|idx| mobility::put(&b, idx))
+            .collect();
+        // wait until every reply is searched to depth 5
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        while !replies
+            .iter()
+            .all(|p| matches!(state.lock().unwrap().get(p), Some((depth, _)) if *depth >= 5))
+        {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the replies were not searched to depth 5 in time"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        // pondering has no limit of its own, only the flag ends it
+        ponder.stop.store(true, Ordering::Relaxed);
+        let handle = ponder.handle.take().unwrap();
+        handle.join().unwrap();
+    }
+}
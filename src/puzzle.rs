@@ -0,0 +1,190 @@
+//! Endgame puzzles mined from games
+//!
+//! A puzzle is a position of a game whose best move is unique, proven by
+//! solving every legal move. Puzzles are written like the FFO problems, which
+//! `--analyze` reads back.
+
+use super::board::{self, Board};
+use super::game::{Color, Game, GameError};
+use super::mobility;
+use super::search::{self, SearchOptions};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleOptions {
+    pub min_empties: u8,
+    pub max_empties: u8,
+}
+
+impl Default for PuzzleOptions {
+    fn default() -> PuzzleOptions {
+        PuzzleOptions {
+            min_empties: 10,
+            max_empties: 24,
+        }
+    }
+}
+
+/// By the nodes needed to solve all moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    /// Fewer than 100k nodes
+    Easy,
+    /// Fewer than 1M nodes
+    Medium,
+    /// Fewer than 10M nodes
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn of_nodes(nodes: u64) -> Difficulty {
+        match nodes {
+            0..=99_999 => Difficulty::Easy,
+            100_000..=999_999 => Difficulty::Medium,
+            1_000_000..=9_999_999 => Difficulty::Hard,
+            _ => Difficulty::Expert,
+        }
+    }
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    /// From the point of view of `color`
+    pub board: Board,
+    pub color: Color,
+    /// Exact score of every legal move, the best first
+    pub scores: Vec<(u8, i8)>,
+    pub nodes: u64,
+    pub difficulty: Difficulty,
+}
+
+impl Puzzle {
+    /// `--XXXXX--... X; G8:+18; H1:+12; # 14 empties, medium, 123456 nodes`
+    pub fn to_ffo_line(&self) -> String {
+        let (squares, side) = match self.color {
+            Color::Black => (board::serialize(&self.board), 'X'),
+            Color::White => (board::serialize(&self.board.pass()), 'O'),
+        };
+        let scores: Vec<String> = self
+            .scores
+            .iter()
+            .map(|(idx, score)| format!("{}:{:+};", board::square_name(*idx).to_uppercase(), score))
+            .collect();
+        format!(
+            "{} {}; {} # {} empties, {}, {} nodes",
+            squares,
+            side,
+            scores.join(" "),
+            (self.board.me | self.board.opp).count_zeros(),
+            self.difficulty,
+            self.nodes
+        )
+    }
+}
+
+/// Solve every legal move of the position.
+///
+/// A puzzle if there are at least two moves and the best one is unique.
+pub fn check_position(b: &Board, color: Color, options: &SearchOptions) -> Option<Puzzle> {
+    if mobility::get_mobility(b).count_ones() < 2 {
+        return None;
+    }
+    let mut nodes = 0;
+    let mut scores: Vec<(u8, i8)> = mobility::legal_moves(b)
+        .map(|idx| {
+            let result = search::complete_search_with(&mobility::put(b, idx), options);
+            nodes += result.stats.nodes;
+            (idx, -result.score)
+        })
+        .collect();
+    scores.sort_by_key(|(idx, score)| (-score, *idx));
+    if scores[0].1 == scores[1].1 {
+        return None;
+    }
+    Some(Puzzle {
+        board: b.clone(),
+        color,
+        scores,
+        nodes,
+        difficulty: Difficulty::of_nodes(nodes),
+    })
+}
+
+/// Puzzles among the positions of the game within the empties of the options.
+///
+/// Positions in `seen`, by their canonical board, are skipped, and the
+/// checked ones are added.
+pub fn find_puzzles(
+    game: &Game,
+    options: &PuzzleOptions,
+    seen: &mut HashSet<Board>,
+) -> Result<Vec<Puzzle>, GameError> {
+    let search_options = SearchOptions {
+        rules: game.rules,
+        ..SearchOptions::default()
+    };
+    let mut puzzles = vec![];
+    for (b, color) in game.replay()? {
+        let empties = (b.me | b.opp).count_zeros() as u8;
+        if empties < options.min_empties || empties > options.max_empties {
+            continue;
+        }
+        if !seen.insert(b.canonical()) {
+            continue;
+        }
+        if let Some(puzzle) = check_position(&b, color, &search_options) {
+            puzzles.push(puzzle);
+        }
+    }
+    Ok(puzzles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze;
+    use crate::strength::{self, Strength};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_find_puzzles() {
+        // 10 empties of FFO #1, Black to move
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let start = [62, 55, 56, 40]
+            .iter()
+            .fold(b, |b, idx| mobility::try_put(&b, *idx).unwrap());
+        let mut game = Game::new(start, Color::Black);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        strength::play_out(&mut game, &Strength::level(1), &mut rng).unwrap();
+
+        let options = PuzzleOptions {
+            min_empties: 4,
+            max_empties: 10,
+        };
+        let mut seen = HashSet::new();
+        let puzzles = find_puzzles(&game, &options, &mut seen).unwrap();
+        assert!(!puzzles.is_empty());
+        for p in &puzzles {
+            assert!(p.scores[0].1 > p.scores[1].1);
+            assert_eq!(p.scores[0].1, search::complete_search(&p.board).score);
+            let line = p.to_ffo_line();
+            let parsed = analyze::parse_position(&line).unwrap();
+            assert_eq!((parsed.board, parsed.color), (p.board.clone(), p.color));
+        }
+        // every position was seen already
+        assert!(find_puzzles(&game, &options, &mut seen).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use super::board::{self, Bits, Board, Size};
+use super::game::Color;
+use super::mobility;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Ascii,
+    /// Full-width glyphs, two columns per square
+    Unicode,
+    /// Unicode with ANSI colours
+    Ansi,
+}
+
+impl std::str::FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Style, String> {
+        match s {
+            "ascii" => Ok(Style::Ascii),
+            "unicode" => Ok(Style::Unicode),
+            "ansi" => Ok(Style::Ansi),
+            _ => Err(format!("unknown style {}", s)),
+        }
+    }
+}
+
+/// Terminal rendering of a board
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub style: Style,
+    /// Side to move, shown as Black / White when known
+    pub color: Option<Color>,
+    pub coordinates: bool,
+    pub mobility: bool,
+    pub last_move: Option<u8>,
+    /// Append the serialized board and the raw bitboards
+    pub debug: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer {
+            style: Style::Unicode,
+            color: None,
+            coordinates: true,
+            mobility: true,
+            last_move: None,
+            debug: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Square {
+    Black,
+    White,
+    Me,
+    Opp,
+    Move,
+    Empty,
+}
+
+impl Renderer {
+    fn glyph(&self, square: Square, last: bool) -> &'static str {
+        match (self.style, square, last) {
+            (Style::Ascii, Square::Black, false) | (Style::Ascii, Square::Me, false) => "X ",
+            (Style::Ascii, Square::Black, true) | (Style::Ascii, Square::Me, true) => "x ",
+            (Style::Ascii, Square::White, false) | (Style::Ascii, Square::Opp, false) => "O ",
+            (Style::Ascii, Square::White, true) | (Style::Ascii, Square::Opp, true) => "o ",
+            (Style::Ascii, Square::Move, _) => "* ",
+            (Style::Ascii, Square::Empty, _) => ". ",
+            (Style::Unicode, Square::Black, false) => "⚫",
+            (Style::Unicode, Square::Black, true) => "⬛",
+            (Style::Unicode, Square::White, false) => "⚪",
+            (Style::Unicode, Square::White, true) => "⬜",
+            (Style::Unicode, Square::Me, false) => "ｘ",
+            (Style::Unicode, Square::Me, true) => "Ｘ",
+            (Style::Unicode, Square::Opp, false) => "ｏ",
+            (Style::Unicode, Square::Opp, true) => "Ｏ",
+            (Style::Unicode, Square::Move, _) => "＿",
+            (Style::Unicode, Square::Empty, _) => "　",
+            (Style::Ansi, Square::Black, false) | (Style::Ansi, Square::Me, false) => {
+                "\x1b[42;30m●\x1b[0m"
+            }
+            (Style::Ansi, Square::Black, true) | (Style::Ansi, Square::Me, true) => {
+                "\x1b[41;30m●\x1b[0m"
+            }
+            (Style::Ansi, Square::White, false) | (Style::Ansi, Square::Opp, false) => {
+                "\x1b[42;97m●\x1b[0m"
+            }
+            (Style::Ansi, Square::White, true) | (Style::Ansi, Square::Opp, true) => {
+                "\x1b[41;97m●\x1b[0m"
+            }
+            (Style::Ansi, Square::Move, _) => "\x1b[42;33m·\x1b[0m",
+            (Style::Ansi, Square::Empty, _) => "\x1b[42m \x1b[0m",
+        }
+    }
+
+    fn square<S: Size>(&self, b: &Board<S>, mobility: S::Bits, idx: u8) -> Square {
+        let (me, opp) = match self.color {
+            Some(Color::Black) => (Square::Black, Square::White),
+            Some(Color::White) => (Square::White, Square::Black),
+            None => (Square::Me, Square::Opp),
+        };
+        let position = S::Bits::ONE << idx as u32;
+        if b.me & position != S::Bits::ZERO {
+            me
+        } else if b.opp & position != S::Bits::ZERO {
+            opp
+        } else if mobility & position != S::Bits::ZERO {
+            Square::Move
+        } else {
+            Square::Empty
+        }
+    }
+
+    pub fn render<S: Size>(&self, b: &Board<S>) -> String {
+        let mobility = if self.mobility {
+            mobility::get_mobility(b)
+        } else {
+            S::Bits::ZERO
+        };
+        // rows from 10 take two columns
+        let label_width = if S::N >= 10 { 2 } else { 1 };
+        let mut buf = String::new();
+        if self.coordinates {
+            buf.push_str(&" ".repeat(label_width + 1));
+            let columns: Vec<String> = (0..S::N)
+                .map(|x| match self.style {
+                    Style::Unicode => char::from_u32('Ａ' as u32 + x).unwrap().to_string(),
+                    Style::Ascii | Style::Ansi => ((b'A' + x as u8) as char).to_string(),
+                })
+                .collect();
+            buf.push_str(&match self.style {
+                Style::Unicode => columns.concat(),
+                Style::Ascii | Style::Ansi => columns.join(" "),
+            });
+            buf.push('\n');
+        }
+        for y in 0..S::N as u8 {
+            if self.coordinates {
+                buf.push_str(&format!("{:>1$} ", y + 1, label_width));
+            }
+            for x in 0..S::N as u8 {
+                let idx = y * S::N as u8 + x;
+                let square = self.square(b, mobility, idx);
+                buf.push_str(self.glyph(square, self.last_move == Some(idx)));
+                if self.style == Style::Ansi {
+                    buf.push_str(self.glyph(Square::Empty, false));
+                }
+            }
+            buf.truncate(buf.trim_end_matches(' ').len());
+            buf.push('\n');
+        }
+
+        let (me, opp) = (b.me.count_ones(), b.opp.count_ones());
+        buf.push_str(&match self.color {
+            Some(Color::Black) => format!("Black {} - White {}, Black to move", me, opp),
+            Some(Color::White) => format!("Black {} - White {}, White to move", opp, me),
+            None => format!("me {} - opp {}", me, opp),
+        });
+        if let Some(idx) = self.last_move {
+            buf.push_str(&format!(", last move {}", board::square_name_in::<S>(idx)));
+        }
+        if self.debug {
+            buf.push_str(&format!("\nSer : {}\nDbg : {:?}", board::serialize(b), b));
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let renderer = Renderer {
+            style: Style::Ascii,
+            color: Some(Color::White),
+            last_move: Some(37),
+            ..Renderer::default()
+        };
+        let expected = "  A B C D E F G H
+1 . . . . . . . .
+2 . . . . . . . .
+3 . . . . . . . .
+4 . . . O X * . .
+5 . . . X X x . .
+6 . . . * . * . .
+7 . . . . . . . .
+8 . . . . . . . .
+Black 4 - White 1, White to move, last move f5";
+        assert_eq!(renderer.render(&Board::new()), expected);
+
+        let debug = Renderer {
+            debug: true,
+            ..Renderer::default()
+        };
+        assert!(debug.render(&Board::new()).contains("\nSer : "));
+        assert!(!Renderer::default().render(&Board::new()).contains("Ser"));
+
+        // every square of the unicode style takes two columns
+        let width = |c: char| match c {
+            ' '..='~' => 1,
+            '⚫' | '⚪' | '⬛' | '⬜' | '　' | 'Ａ'..='ｚ' => 2,
+            _ => panic!("unknown width of {}", c),
+        };
+        for color in [Some(Color::Black), None] {
+            let unicode = Renderer {
+                color,
+                last_move: Some(37),
+                ..Renderer::default()
+            };
+            let lines: Vec<usize> = unicode
+                .render(&Board::new())
+                .lines()
+                .take(9)
+                .map(|line| line.chars().map(width).sum())
+                .collect();
+            assert_eq!(lines, vec![18; 9]);
+        }
+    }
+}
@@ -1,59 +1,469 @@
-use super::board::Board;
+use super::board::{self, Bits, Board, Size, Size8};
+use super::cache::{Solved, SolvedCache};
+use super::game::Rules;
 use super::mobility;
+use super::ordering::{self, History, Killers, OrderingStage, OrderingStats, STAGES};
+use super::time::Instant;
+use super::tt::{self, Entry, TranspositionTable};
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Mutex, MutexGuard};
 use std::time::Duration;
-use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub idx: u8,
     pub score: i8,
-    pub searched: u32,
     pub game_end: bool,
+    /// Accumulated over all iterations
+    pub stats: SearchStats,
 }
 
-fn result_of(idx: u8, score: i8, searched: u32, game_end: bool) -> SearchResult {
+fn result_of(idx: u8, score: i8, game_end: bool) -> SearchResult {
     SearchResult {
         idx,
         score,
-        searched,
         game_end,
+        stats: SearchStats::default(),
     }
 }
 
+/// Beta cuts are counted by the first moves up to this, and the rest together
+pub const CUTOFF_MOVES: usize = 4;
+
+/// Counters of a search
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Positions visited, including leaves
+    pub nodes: u64,
+    /// Evaluations at the depth limit and scores of finished games
+    pub leaves: u64,
+    /// Beta cuts by the position of the cutting move in the search order,
+    /// the last one counting all later moves
+    pub cutoffs: [u64; CUTOFF_MOVES],
+    /// Root searches repeated since the score fell outside the aspiration window
+    pub researches: u64,
+    /// Transposition table probes which found the position
+    pub tt_hits: u64,
+    /// Of those, the stored bound decided the score
+    pub tt_cuts: u64,
+    /// Nodes whose children were probed by enhanced transposition cutoff
+    pub etc_nodes: u64,
+    /// Of those, a child's entry proved a beta cut before any search
+    pub etc_cuts: u64,
+    /// Nodes by ply from the searched position
+    pub depth_nodes: Vec<u64>,
+}
+
+impl SearchStats {
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.leaves += other.leaves;
+        for (c, o) in self.cutoffs.iter_mut().zip(&other.cutoffs) {
+            *c += o;
+        }
+        self.researches += other.researches;
+        self.tt_hits += other.tt_hits;
+        self.tt_cuts += other.tt_cuts;
+        self.etc_nodes += other.etc_nodes;
+        self.etc_cuts += other.etc_cuts;
+        if self.depth_nodes.len() < other.depth_nodes.len() {
+            self.depth_nodes.resize(other.depth_nodes.len(), 0);
+        }
+        for (d, o) in self.depth_nodes.iter_mut().zip(&other.depth_nodes) {
+            *d += o;
+        }
+    }
+
+    pub fn cut_nodes(&self) -> u64 {
+        self.cutoffs.iter().sum()
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let cut_nodes = self.cut_nodes().max(1) as f64;
+        let rates: Vec<String> = self
+            .cutoffs
+            .iter()
+            .map(|c| format!("{:.1}%", *c as f64 * 100.0 / cut_nodes))
+            .collect();
+        write!(
+            f,
+            "nodes = {}, leaves = {}, tt hits = {} ({} cuts), etc = {} ({} cuts), researches = {}, cutoffs by move = {}",
+            self.nodes,
+            self.leaves,
+            self.tt_hits,
+            self.tt_cuts,
+            self.etc_nodes,
+            self.etc_cuts,
+            self.researches,
+            rates.join(" ")
+        )
+    }
+}
+
+/// Score of a node inside the search
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    idx: u8,
+    score: i8,
+    game_end: bool,
+}
+
+fn node_of(idx: u8, score: i8, game_end: bool) -> Node {
+    Node {
+        idx,
+        score,
+        game_end,
+    }
+}
+
+const MIN_DEPTH: u8 = 5;
 const MAX_DEPTH: u8 = 20;
+const STABLE_ITERATIONS: u8 = 3;
+/// Half width of the first aspiration window, doubled on every re-search
+pub const ASPIRATION_WINDOW: i8 = 4;
+
+/// Progress of a search, passed to a `SearchObserver`
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub score: i8,
+    /// Principal variation from the searched position, passes omitted
+    pub pv: Vec<u8>,
+    pub elapsed: Duration,
+    /// The score is exact since the game end was reached
+    pub game_end: bool,
+    /// Accumulated over the iterations so far
+    pub ordering: OrderingStats,
+    /// Of this iteration
+    pub stats: SearchStats,
+}
+
+/// Receives the progress of a search instead of printing it
+pub trait SearchObserver {
+    /// Called after every completed iteration
+    fn iteration(&self, info: &SearchInfo);
+
+    /// Other events, e.g. an aborted iteration
+    fn note(&self, _message: &str) {}
+}
+
+/// Prints the progress to stdout
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleObserver;
+
+impl SearchObserver for ConsoleObserver {
+    fn iteration(&self, info: &SearchInfo) {
+        let pv: String = info.pv.iter().map(|idx| board::square_name(*idx)).collect();
+        println!(
+            "searched depth#{}, score = {}, nodes = {}, researches = {}, time = {}ms, pv = {}",
+            info.depth,
+            info.score,
+            info.stats.nodes,
+            info.stats.researches,
+            info.elapsed.as_millis(),
+            pv
+        );
+    }
+
+    fn note(&self, message: &str) {
+        println!("{}", message);
+    }
+}
 
-pub fn complete_search(b: &Board) -> SearchResult {
-    let depth = (b.me | b.opp).count_zeros() as u8 + 1;
-    let result = rec_search(b, 0, depth, -i8::MAX, i8::MAX, &None).unwrap();
-    println!("searched depth#{}, result = {:?}", depth, result);
+pub fn complete_search<S: Size>(b: &Board<S>) -> SearchResult {
+    solve(b, &SearchOptions::default())
+}
+
+/// `complete_search` with the rules, ordering, cache and observer of the options.
+///
+/// The search is never aborted, so `limit`, `stop` and `state` are ignored.
+pub fn complete_search_with(b: &Board, options: &SearchOptions) -> SearchResult {
+    if let Some(cache) = options.cache {
+        if let Some(solved) = cache.lock().unwrap().get(b, options.rules) {
+            if let Some(observer) = options.observer {
+                observer.note("found in the solved position cache");
+            }
+            return result_of(solved.best, solved.score, true);
+        }
+    }
+    let result = solve(b, options);
+    if let Some(cache) = options.cache {
+        let solved = Solved {
+            score: result.score,
+            // the move of the opponent after a pass, as a search would return
+            best: result.idx,
+        };
+        cache.lock().unwrap().insert(b, options.rules, solved);
+    }
     result
 }
 
+/// `complete_search_with` on a board of any size, without the cache
+pub fn solve<S: Size>(b: &Board<S>, options: &SearchOptions<S>) -> SearchResult {
+    let started = Instant::now();
+    let depth = b.empties() as u8 + 1;
+    let mut searcher = Searcher::new(options, depth);
+    let node = searcher
+        .root_search(b, 0, depth, -100, 100, &NO_ABORT)
+        .unwrap();
+    let stats = searcher.take_stats();
+    if let Some(observer) = options.observer {
+        observer.iteration(&searcher.info(depth, &node, &stats, started));
+    }
+    SearchResult {
+        stats,
+        ..result_of(node.idx, node.score, node.game_end)
+    }
+}
+
+/// Time to spend on a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeLimit {
+    /// No new iteration is started after this
+    pub soft: Duration,
+    /// The search is aborted at this point
+    pub hard: Duration,
+}
+
+impl TimeLimit {
+    pub fn fixed(ms: u64) -> TimeLimit {
+        TimeLimit {
+            soft: Duration::from_millis(ms),
+            hard: Duration::from_millis(ms),
+        }
+    }
+}
+
+/// Results kept between searches, so that pondering or a previous search
+/// lets the next search start from a deeper iteration.
+#[derive(Debug, Default)]
+pub struct SearchState<S: Size = Size8> {
+    /// The deepest completed iteration for each position
+    results: HashMap<Board<S>, (u8, SearchResult)>,
+}
+
+impl<S: Size> SearchState<S> {
+    pub fn clear(&mut self) {
+        self.results.clear();
+    }
+
+    /// Depth and result of the deepest completed iteration
+    pub fn get(&self, b: &Board<S>) -> Option<&(u8, SearchResult)> {
+        self.results.get(b)
+    }
+
+    fn update(&mut self, b: &Board<S>, depth: u8, result: &SearchResult) {
+        match self.results.get(b) {
+            Some((d, _)) if *d >= depth => {}
+            _ => {
+                self.results.insert(b.clone(), (depth, result.clone()));
+            }
+        }
+    }
+}
+
+/// Parameters of `iterative_search`
+#[derive(Default)]
+pub struct SearchOptions<'a, S: Size = Size8> {
+    /// Search until `MAX_DEPTH` or `stop` without a limit
+    pub limit: Option<TimeLimit>,
+    /// Aborts the search when set from another thread
+    pub stop: Option<&'a AtomicBool>,
+    pub state: Option<&'a Mutex<SearchState<S>>>,
+    /// Silent without an observer
+    pub observer: Option<&'a dyn SearchObserver>,
+    pub rules: Rules,
+    /// `ordering::DEFAULT_ORDERING` if not given
+    pub ordering: Option<&'a [OrderingStage]>,
+    /// Deepest iteration, `MAX_DEPTH` if not given
+    pub max_depth: Option<u8>,
+    /// Half width of the aspiration window around the score of the previous
+    /// iteration, `ASPIRATION_WINDOW` if not given, 0 for the full window
+    pub aspiration: Option<i8>,
+    /// Exact results consulted and updated by `complete_search_with`, on 8x8 only
+    pub cache: Option<&'a Mutex<SolvedCache>>,
+    /// Kept between searches under the same rules; without it, each search
+    /// allocates a table sized by the plies it can reach
+    pub tt: Option<&'a Mutex<TranspositionTable<S>>>,
+}
+
 pub fn find_best_move(b: &Board, ms: u64) -> u8 {
+    find_best_move_within(b, &TimeLimit::fixed(ms))
+}
+
+pub fn find_best_move_within(b: &Board, limit: &TimeLimit) -> u8 {
+    let options = SearchOptions {
+        limit: Some(limit.clone()),
+        ..SearchOptions::default()
+    };
+    iterative_search(b, &options).idx
+}
+
+/// Iterative deepening until the limit.
+///
+/// Stops early when the best move has not changed for a while.
+/// With a state, the search resumes after the deepest iteration stored there.
+pub fn iterative_search<S: Size>(b: &Board<S>, options: &SearchOptions<S>) -> SearchResult {
+    let log = |s: String| {
+        if let Some(observer) = options.observer {
+            observer.note(&s);
+        }
+    };
     if let Some(r) = one_mobility_check(b) {
-        println!("no choice but {}", r);
-        return r;
-    }
-    let mut result = result_of(u8::MAX, 0, 0, false);
-    let deadline = Instant::now().checked_add(Duration::from_millis(ms));
-    for depth in 5..=MAX_DEPTH {
-        if let Some(r) = rec_search(b, 0, depth, -100, 100, &deadline) {
-            result = r;
-            println!("searched depth#{}, result = {:?}", depth, result);
+        log(format!("no choice but {}", r));
+        return result_of(r, 0, false);
+    }
+    let started = Instant::now();
+    // fallback in case even the first iteration is aborted
+    let mut result = result_of(mobility::get_mobility(b).trailing_zeros() as u8, 0, false);
+    let mut min_depth = MIN_DEPTH;
+    if let Some(state) = options.state {
+        if let Some((depth, r)) = state.lock().unwrap().get(b) {
+            log(format!("resumed from depth#{}, result = {:?}", depth, r));
+            result = SearchResult {
+                stats: SearchStats::default(),
+                ..r.clone()
+            };
+            if result.game_end {
+                return result;
+            }
+            min_depth = depth + 1;
+        }
+    }
+
+    let mut stable = 0;
+    let abort = Abort {
+        deadline: options
+            .limit
+            .as_ref()
+            .and_then(|l| started.checked_add(l.hard)),
+        stop: options.stop,
+    };
+    let window = options.aspiration.unwrap_or(ASPIRATION_WINDOW);
+    // no score to center the window on before the first iteration
+    let mut guess = (min_depth > MIN_DEPTH).then_some(result.score);
+    let max_depth = options.max_depth.unwrap_or(MAX_DEPTH);
+    let mut searcher = Searcher::new(options, max_depth.min(b.empties() as u8));
+    for depth in min_depth..=max_depth {
+        let searched = match guess {
+            Some(score) if window > 0 => {
+                searcher.aspiration_search(b, depth, score, window, &abort)
+            }
+            _ => searcher.root_search(b, 0, depth, -100, 100, &abort),
+        };
+        let stats = searcher.take_stats();
+        result.stats.merge(&stats);
+        if let Some(node) = searched {
+            stable = if depth > min_depth && node.idx == result.idx {
+                stable + 1
+            } else {
+                0
+            };
+            result.idx = node.idx;
+            result.score = node.score;
+            result.game_end = node.game_end;
+            guess = Some(node.score);
+            if let Some(observer) = options.observer {
+                observer.iteration(&searcher.info(depth, &node, &stats, started));
+            }
+            if let Some(state) = options.state {
+                state.lock().unwrap().update(b, depth, &result);
+            }
             if result.game_end {
-                println!("search completed depth#{}", depth);
-                return result.idx;
+                log(format!("search completed depth#{}", depth));
+                return result;
             }
         } else {
-            println!("aborted depth#{}", depth);
+            log(format!("aborted depth#{}", depth));
             break;
         }
+        if let Some(limit) = &options.limit {
+            let elapsed = started.elapsed();
+            if elapsed >= limit.soft {
+                break;
+            }
+            if stable >= STABLE_ITERATIONS && elapsed >= limit.soft / 2 {
+                log(format!(
+                    "best move is stable since depth#{}",
+                    depth - stable
+                ));
+                break;
+            }
+        }
     }
-    result.idx
+    result
 }
 
-fn one_mobility_check(b: &Board) -> Option<u8> {
+/// Score of every legal move at the deepest iteration completed within the limit.
+///
+/// Unlike `iterative_search`, every move gets an exact score by the full window.
+pub fn score_moves(b: &Board, rules: Rules, max_depth: u8, limit: &TimeLimit) -> Vec<(u8, i8)> {
+    let moves: Vec<(u8, Board)> = mobility::legal_moves(b)
+        .map(|idx| (idx, mobility::put(b, idx)))
+        .collect();
+    let abort = Abort {
+        deadline: Instant::now().checked_add(limit.hard),
+        stop: None,
+    };
+    let options = SearchOptions {
+        rules,
+        ..SearchOptions::default()
+    };
+    let mut searcher = Searcher::new(&options, max_depth.min(b.empties() as u8));
+    let mut scores: Vec<(u8, i8)> = moves.iter().map(|(idx, _)| (*idx, 0)).collect();
+    for depth in 1..=max_depth.max(1) {
+        let mut current = vec![];
+        let mut game_end = true;
+        // the first iteration always completes, so every move has a score
+        let abort = if depth == 1 { &NO_ABORT } else { &abort };
+        for (idx, next) in &moves {
+            match searcher.root_search(next, 1, depth, -100, 100, abort) {
+                Some(r) => {
+                    current.push((*idx, -r.score));
+                    game_end &= r.game_end;
+                }
+                None => return scores,
+            }
+        }
+        scores = current;
+        if game_end || abort.is_reached() {
+            break;
+        }
+    }
+    scores
+}
+
+/// When to give up an iteration, checked every `POLL_NODES` nodes
+struct Abort<'a> {
+    deadline: Option<Instant>,
+    stop: Option<&'a AtomicBool>,
+}
+
+const NO_ABORT: Abort<'static> = Abort {
+    deadline: None,
+    stop: None,
+};
+
+impl Abort<'_> {
+    fn is_reached(&self) -> bool {
+        if let Some(stop) = self.stop {
+            if stop.load(atomic::Ordering::Relaxed) {
+                return true;
+            }
+        }
+        if let Some(d) = self.deadline {
+            return Instant::now().saturating_duration_since(d).as_nanos() > 0;
+        }
+        false
+    }
+}
+
+fn one_mobility_check<S: Size>(b: &Board<S>) -> Option<u8> {
     let m = mobility::get_mobility(b);
     if m.count_ones() == 1 {
         Some(m.trailing_zeros() as u8)
@@ -62,142 +472,511 @@ fn one_mobility_check(b: &Board) -> Option<u8> {
     }
 }
 
-fn rec_search(
-    b: &Board,
-    depth: u8,
-    max_depth: u8,
-    alpha: i8,
-    beta: i8,
-    deadline: &Option<Instant>,
-) -> Option<SearchResult> {
-    let occupied = !(b.me | b.opp);
-    if depth >= max_depth || occupied == 0 {
-        return Some(result_of(u8::MAX, evaluate(b), 1, occupied == 0));
+/// Size of the triangular table, enough for a search from any position
+const PV_SIZE: usize = board::MAX_SQUARES;
+
+/// Triangular table of the principal variation
+///
+/// `moves[ply][ply..len[ply]]` is the best line found from the node at `ply`.
+struct Pv {
+    moves: [[u8; PV_SIZE]; PV_SIZE],
+    len: [u8; PV_SIZE],
+}
+
+impl Pv {
+    fn new() -> Box<Pv> {
+        Box::new(Pv {
+            moves: [[u8::MAX; PV_SIZE]; PV_SIZE],
+            len: [0; PV_SIZE],
+        })
     }
 
-    let mut mobility = mobility::get_mobility(b);
-    if mobility == 0 {
-        let passed = b.pass();
-        if mobility::get_mobility(&passed) == 0 {
-            // game end
-            return Some(result_of(u8::MAX, evaluate(b), 1, true));
-        } else {
-            // pass
-            return rec_search(&passed, depth, max_depth, -beta, -alpha, &None).map(|r| {
-                SearchResult {
-                    score: -r.score,
-                    ..r
-                }
-            });
+    #[inline]
+    fn clear(&mut self, ply: u8) {
+        self.len[ply as usize] = ply;
+    }
+
+    /// `idx` followed by the line of the child
+    #[inline]
+    fn update(&mut self, ply: u8, idx: u8) {
+        let ply = ply as usize;
+        let end = (self.len[ply + 1] as usize).max(ply + 1);
+        let (parent, child) = self.moves.split_at_mut(ply + 1);
+        parent[ply][ply] = idx;
+        parent[ply][ply + 1..end].copy_from_slice(&child[0][ply + 1..end]);
+        self.len[ply] = end as u8;
+    }
+
+    fn line(&self) -> Vec<u8> {
+        self.moves[0][..self.len[0] as usize].to_vec()
+    }
+}
+
+/// Plies from the leaf below which moves are searched in plain bit order
+const UNORDERED_PLIES: u8 = 6;
+/// Plies searched to order moves by `OrderingStage::ShallowSearch`
+const SHALLOW_PLIES: u8 = 2;
+/// Nodes at least this far from the leaf are ordered by a shallow search
+const SHALLOW_MIN_PLIES: u8 = 10;
+/// Children are probed for enhanced transposition cutoff from this many empties,
+/// when the search reaches the game end
+const ETC_MIN_EMPTIES: u8 = 10;
+/// Enough for the legal moves of any position, which are at most its empties
+const MAX_MOVES: usize = board::MAX_SQUARES - 4;
+/// Nodes between two checks of the deadline and the stop flag
+const POLL_NODES: u64 = 1024;
+
+/// Mutable state of one search, kept over its iterations
+struct Searcher<'a, S: Size> {
+    rules: Rules,
+    ordering: &'a [OrderingStage],
+    pv: Box<Pv>,
+    tt: Table<'a, S>,
+    killers: Killers,
+    history: History,
+    ordering_stats: OrderingStats,
+    /// Of the current iteration
+    stats: SearchStats,
+}
+
+/// The table of `SearchOptions::tt`, locked for the whole search, or one of
+/// the search's own
+enum Table<'a, S: Size> {
+    Owned(TranspositionTable<S>),
+    Shared(MutexGuard<'a, TranspositionTable<S>>),
+}
+
+impl<S: Size> Deref for Table<'_, S> {
+    type Target = TranspositionTable<S>;
+
+    fn deref(&self) -> &TranspositionTable<S> {
+        match self {
+            Table::Owned(tt) => tt,
+            Table::Shared(tt) => tt,
         }
     }
+}
 
-    let mut best: SearchResult = result_of(u8::MAX, alpha, 0, false);
-    let mut first = true;
-    if max_depth - depth <= 6 {
-        while mobility != 0 {
-            let idx = mobility.trailing_zeros() as u8;
-            mobility ^= 1 << idx;
-            if search_for_idx(b, idx, depth, max_depth, beta, &mut best, first) {
-                break;
+impl<S: Size> DerefMut for Table<'_, S> {
+    fn deref_mut(&mut self) -> &mut TranspositionTable<S> {
+        match self {
+            Table::Owned(tt) => tt,
+            Table::Shared(tt) => tt,
+        }
+    }
+}
+
+impl<'a, S: Size> Searcher<'a, S> {
+    /// `plies` is the deepest the search can go, which sizes its own table
+    fn new(options: &SearchOptions<'a, S>, plies: u8) -> Searcher<'a, S> {
+        Searcher {
+            rules: options.rules,
+            ordering: options.ordering.unwrap_or(ordering::DEFAULT_ORDERING),
+            pv: Pv::new(),
+            tt: match options.tt {
+                Some(tt) => Table::Shared(tt.lock().unwrap()),
+                None => Table::Owned(TranspositionTable::new(tt::bits_for(plies))),
+            },
+            killers: Killers::new(),
+            history: History::new(),
+            ordering_stats: OrderingStats::default(),
+            stats: SearchStats {
+                depth_nodes: vec![0; PV_SIZE + 1],
+                ..SearchStats::default()
+            },
+        }
+    }
+
+    /// Statistics since the last call
+    fn take_stats(&mut self) -> SearchStats {
+        let mut stats = SearchStats {
+            depth_nodes: vec![0; PV_SIZE + 1],
+            ..SearchStats::default()
+        };
+        std::mem::swap(&mut stats, &mut self.stats);
+        while stats.depth_nodes.last() == Some(&0) {
+            stats.depth_nodes.pop();
+        }
+        stats
+    }
+
+    /// Search of the rules within the window `alpha..beta`
+    ///
+    /// `depth` is usually 0, or 1 to search positions after each root move.
+    fn root_search(
+        &mut self,
+        b: &Board<S>,
+        depth: u8,
+        max_depth: u8,
+        alpha: i8,
+        beta: i8,
+        abort: &Abort,
+    ) -> Option<Node> {
+        match self.rules {
+            Rules::Standard => self.rec_search::<false>(b, depth, max_depth, alpha, beta, abort),
+            Rules::Anti => self.rec_search::<true>(b, depth, max_depth, alpha, beta, abort),
+        }
+    }
+
+    /// Search within `window` around `guess`, widening the side the score
+    /// fell out of until it lies inside.
+    ///
+    /// The re-searches are counted in the statistics.
+    fn aspiration_search(
+        &mut self,
+        b: &Board<S>,
+        max_depth: u8,
+        guess: i8,
+        window: i8,
+        abort: &Abort,
+    ) -> Option<Node> {
+        let mut delta = window as i16;
+        let mut alpha = (guess as i16 - delta).max(-100) as i8;
+        let mut beta = (guess as i16 + delta).min(100) as i8;
+        loop {
+            let r = self.root_search(b, 0, max_depth, alpha, beta, abort)?;
+            delta *= 2;
+            if r.score <= alpha && alpha > -100 {
+                alpha = (alpha as i16 - delta).max(-100) as i8;
+            } else if r.score >= beta && beta < 100 {
+                beta = (beta as i16 + delta).min(100) as i8;
+            } else {
+                return Some(r);
             }
-            first = false;
+            self.stats.researches += 1;
         }
-    } else {
-        let mut moves: [(u8, u8); 30] = [(u8::MAX, u8::MAX); 30];
-        fastest_first_ordering(b, mobility, &mut moves);
-        for (idx, m) in moves {
-            if idx == u8::MAX || m == u8::MAX {
-                continue;
+    }
+
+    fn info(&self, depth: u8, node: &Node, stats: &SearchStats, started: Instant) -> SearchInfo {
+        SearchInfo {
+            depth,
+            score: node.score,
+            pv: self.pv.line(),
+            elapsed: started.elapsed(),
+            game_end: node.game_end,
+            ordering: self.ordering_stats.clone(),
+            stats: stats.clone(),
+        }
+    }
+
+    /// `ANTI` selects anti-reversi, where fewer discs win
+    fn rec_search<const ANTI: bool>(
+        &mut self,
+        b: &Board<S>,
+        depth: u8,
+        max_depth: u8,
+        alpha: i8,
+        beta: i8,
+        abort: &Abort,
+    ) -> Option<Node> {
+        self.pv.clear(depth);
+        self.stats.nodes += 1;
+        self.stats.depth_nodes[depth as usize] += 1;
+        if self.stats.nodes % POLL_NODES == 0 && abort.is_reached() {
+            return None;
+        }
+        let empties = b.empties() as u8;
+        if empties == 0 {
+            self.stats.leaves += 1;
+            return Some(node_of(u8::MAX, final_score::<S, ANTI>(b), true));
+        }
+        if depth >= max_depth {
+            self.stats.leaves += 1;
+            return Some(node_of(u8::MAX, evaluate::<S, ANTI>(b), false));
+        }
+
+        let mut mobility = mobility::get_mobility(b);
+        if mobility == S::Bits::ZERO {
+            let passed = b.pass();
+            if mobility::get_mobility(&passed) == S::Bits::ZERO {
+                // game end
+                self.stats.leaves += 1;
+                return Some(node_of(u8::MAX, final_score::<S, ANTI>(b), true));
+            } else {
+                // pass
+                return self
+                    .rec_search::<ANTI>(&passed, depth, max_depth, -beta, -alpha, abort)
+                    .map(|r| Node {
+                        score: -r.score,
+                        ..r
+                    });
             }
-            if search_for_idx(b, idx, depth, max_depth, beta, &mut best, first) {
+        }
+
+        let mut best = node_of(u8::MAX, alpha, false);
+        let mut first = true;
+        let remaining = max_depth - depth;
+        if remaining <= UNORDERED_PLIES {
+            let mut n = 0;
+            while mobility != S::Bits::ZERO {
+                let idx = mobility.trailing_zeros() as u8;
+                mobility &= mobility - S::Bits::ONE;
+                if self.search_for_idx::<ANTI>(
+                    b, idx, depth, max_depth, beta, &mut best, first, abort,
+                )? {
+                    self.count_cutoff(n);
+                    break;
+                }
+                first = false;
+                n += 1;
+            }
+            return Some(best);
+        }
+
+        let mut tt_move = u8::MAX;
+        if let Some(e) = self.tt.get(b) {
+            self.stats.tt_hits += 1;
+            if e.depth >= remaining {
+                let bound = if e.lower >= beta {
+                    Some(e.lower)
+                } else if e.upper <= alpha {
+                    Some(e.upper)
+                } else if e.lower == e.upper {
+                    Some(e.lower)
+                } else {
+                    None
+                };
+                if let Some(score) = bound {
+                    self.stats.tt_cuts += 1;
+                    return Some(node_of(e.best, score, e.game_end));
+                }
+            }
+            tt_move = e.best;
+        }
+        if empties >= ETC_MIN_EMPTIES && remaining > empties {
+            if let Some(node) = self.etc::<ANTI>(b, mobility, depth, remaining, beta) {
+                return Some(node);
+            }
+        }
+
+        let mut moves = [(u8::MAX, [0; STAGES]); MAX_MOVES];
+        let (n, decider) =
+            self.order_moves::<ANTI>(b, mobility, depth, max_depth, tt_move, &mut moves);
+        for (i, (idx, _)) in moves[..n].iter().enumerate() {
+            if self
+                .search_for_idx::<ANTI>(b, *idx, depth, max_depth, beta, &mut best, first, abort)?
+            {
+                self.killers.add(depth, *idx);
+                self.history.add(depth, *idx, remaining);
+                self.count_cutoff(i);
+                self.ordering_stats.cut_nodes += 1;
+                if i == 0 {
+                    self.ordering_stats.first_cutoffs += 1;
+                    if let Some(stage) = decider {
+                        self.ordering_stats.stages[stage as usize].cutoffs += 1;
+                    }
+                }
                 break;
             }
             first = false;
-            if let Some(d) = deadline {
-                if Instant::now().saturating_duration_since(*d).as_nanos() > 0 {
-                    return None;
+        }
+
+        let (lower, upper) = if best.score <= alpha {
+            (-i8::MAX, best.score)
+        } else if best.score >= beta {
+            (best.score, i8::MAX)
+        } else {
+            (best.score, best.score)
+        };
+        self.tt.store(Entry::new(
+            b,
+            remaining,
+            lower,
+            upper,
+            best.idx,
+            best.game_end,
+        ));
+        Some(best)
+    }
+
+    /// Enhanced transposition cutoff
+    ///
+    /// A child whose stored upper bound is at most `-beta` is a beta cut
+    /// without searching any child.
+    fn etc<const ANTI: bool>(
+        &mut self,
+        b: &Board<S>,
+        mut mobility: S::Bits,
+        depth: u8,
+        remaining: u8,
+        beta: i8,
+    ) -> Option<Node> {
+        self.stats.etc_nodes += 1;
+        while mobility != S::Bits::ZERO {
+            let idx = mobility.trailing_zeros() as u8;
+            mobility &= mobility - S::Bits::ONE;
+            let next = mobility::put(b, idx);
+            if let Some(e) = self.tt.get(&next) {
+                if e.depth + 1 >= remaining && -e.upper >= beta {
+                    self.stats.etc_cuts += 1;
+                    self.pv.clear(depth + 1);
+                    self.pv.update(depth, idx);
+                    return Some(node_of(idx, -e.upper, e.game_end));
                 }
             }
         }
+        None
     }
-    Some(best)
-}
 
-/// search by nega-scout
-///
-/// return true if beta cut
-/// return false if search continues
-#[inline]
-fn search_for_idx(
-    b: &Board,
-    idx: u8,
-    depth: u8,
-    max_depth: u8,
-    beta: i8,
-    best: &mut SearchResult,
-    first: bool,
-) -> bool {
-    let next = mobility::put(b, idx);
-    let alpha = best.score;
-
-    let mut result: SearchResult;
-    if first {
-        // actual search
-        result = rec_search(&next, depth + 1, max_depth, -beta, -alpha, &None).unwrap();
-        best.searched += result.searched;
-    } else {
-        // null window search
-        result = rec_search(&next, depth + 1, max_depth, -alpha - 1, -alpha, &None).unwrap();
-        best.searched += result.searched;
-        if alpha < -result.score && -result.score < beta {
-            // actual search
-            result = rec_search(&next, depth + 1, max_depth, -beta, -alpha, &None).unwrap();
-            best.searched += result.searched;
+    #[inline]
+    fn count_cutoff(&mut self, nth: usize) {
+        self.stats.cutoffs[nth.min(CUTOFF_MOVES - 1)] += 1;
+    }
+
+    /// Sort the legal moves by the keys of the ordering stages.
+    ///
+    /// Returns the number of moves and the stage which decided the first one.
+    fn order_moves<const ANTI: bool>(
+        &mut self,
+        b: &Board<S>,
+        mut mobility: S::Bits,
+        depth: u8,
+        max_depth: u8,
+        tt_move: u8,
+        moves: &mut [(u8, [i32; STAGES]); MAX_MOVES],
+    ) -> (usize, Option<OrderingStage>) {
+        let mut n = 0;
+        while mobility != S::Bits::ZERO {
+            let idx = mobility.trailing_zeros() as u8;
+            mobility &= mobility - S::Bits::ONE;
+            moves[n].0 = idx;
+            for (i, stage) in self.ordering.iter().enumerate() {
+                moves[n].1[i] = self.stage_key::<ANTI>(*stage, b, idx, depth, max_depth, tt_move);
+            }
+            n += 1;
         }
+        moves[..n].sort_by_key(|m| m.1);
+
+        if n < 2 {
+            return (n, None);
+        }
+        let decider = (0..self.ordering.len())
+            .find(|i| moves[0].1[*i] != moves[1].1[*i])
+            .map(|i| self.ordering[i]);
+        if let Some(stage) = decider {
+            self.ordering_stats.stages[stage as usize].selected += 1;
+        }
+        (n, decider)
     }
-    let score = -result.score;
-    if score > best.score {
-        best.idx = idx;
-        best.score = score;
-        best.game_end = result.game_end;
+
+    fn stage_key<const ANTI: bool>(
+        &mut self,
+        stage: OrderingStage,
+        b: &Board<S>,
+        idx: u8,
+        depth: u8,
+        max_depth: u8,
+        tt_move: u8,
+    ) -> i32 {
+        match stage {
+            OrderingStage::TtMove => (idx != tt_move) as i32,
+            OrderingStage::Killers => self.killers.rank(depth, idx),
+            OrderingStage::History => -(self.history.get(depth, idx).min(i32::MAX as u32) as i32),
+            OrderingStage::ShallowSearch => {
+                if max_depth - depth < SHALLOW_MIN_PLIES {
+                    return 0;
+                }
+                let next = mobility::put(b, idx);
+                let shallow = depth + 1 + SHALLOW_PLIES;
+                // the score of the opponent, lower is better
+                self.rec_search::<ANTI>(&next, depth + 1, shallow, -100, 100, &NO_ABORT)
+                    .unwrap()
+                    .score as i32
+            }
+            OrderingStage::FastestFirst => fastest_first_key::<S, ANTI>(b, idx),
+        }
     }
-    if score >= beta {
-        return true;
+
+    /// search by nega-scout
+    ///
+    /// return true if beta cut
+    /// return false if search continues
+    /// return None if aborted
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn search_for_idx<const ANTI: bool>(
+        &mut self,
+        b: &Board<S>,
+        idx: u8,
+        depth: u8,
+        max_depth: u8,
+        beta: i8,
+        best: &mut Node,
+        first: bool,
+        abort: &Abort,
+    ) -> Option<bool> {
+        let next = mobility::put(b, idx);
+        let alpha = best.score;
+
+        let mut result: Node;
+        if first {
+            // actual search
+            result = self.rec_search::<ANTI>(&next, depth + 1, max_depth, -beta, -alpha, abort)?;
+        } else {
+            // null window search
+            result =
+                self.rec_search::<ANTI>(&next, depth + 1, max_depth, -alpha - 1, -alpha, abort)?;
+            if alpha < -result.score && -result.score < beta {
+                // actual search
+                result =
+                    self.rec_search::<ANTI>(&next, depth + 1, max_depth, -beta, -alpha, abort)?;
+            }
+        }
+        let score = -result.score;
+        if score > best.score {
+            best.idx = idx;
+            best.score = score;
+            best.game_end = result.game_end;
+            self.pv.update(depth, idx);
+        }
+        Some(score >= beta)
     }
+}
 
-    false
+/// Fewest replies first; under anti-reversi, moves gaining fewer discs are preferred too
+fn fastest_first_key<S: Size, const ANTI: bool>(b: &Board<S>, idx: u8) -> i32 {
+    let next_board = mobility::put(b, idx);
+    let mut key = mobility::get_mobility(&next_board).count_ones() as i32;
+    if ANTI {
+        key += (next_board.opp.count_ones() - b.me.count_ones()) as i32;
+    }
+    key
 }
 
-fn fastest_first_ordering(b: &Board, mobility: u64, moves: &mut [(u8, u8); 30]) {
-    let mut i = 0;
-    for idx in 0..64 {
-        if mobility >> idx & 1 == 0 {
-            continue;
+fn evaluate<S: Size, const ANTI: bool>(b: &Board<S>) -> i8 {
+    let m = b.me.count_ones() as i8;
+    let o = b.opp.count_ones() as i8;
+    if b.empties() > 14 {
+        // evaluate by mobility
+        let mobility = mobility::get_mobility(b).count_ones() as i8
+            - mobility::get_mobility(&b.pass()).count_ones() as i8;
+        if ANTI {
+            // fewer discs also keep the opponent short of safe moves later
+            mobility + (o - m) / 4
+        } else {
+            mobility
         }
-        let next_board = mobility::put(b, idx);
-        moves[i] = (idx, mobility::get_mobility(&next_board).count_ones() as u8);
-        i += 1;
+    } else {
+        final_score::<S, ANTI>(b)
     }
-    moves[0..i].sort_by(|a, b| a.1.cmp(&b.1));
 }
 
-fn evaluate(b: &Board) -> i8 {
+/// Score with the empty squares going to the winner
+fn final_score<S: Size, const ANTI: bool>(b: &Board<S>) -> i8 {
     let m = b.me.count_ones() as i8;
     let o = b.opp.count_ones() as i8;
-    if m + o < 50 {
-        // evaluate by mobility
-        mobility::get_mobility(b).count_ones() as i8
-            - mobility::get_mobility(&b.pass()).count_ones() as i8
+    let squares = S::SQUARES as i8;
+    // Using `m - o` as score is not appropriate in order to pass FFO.
+    // https://github.com/primenumber/issen-rs/blob/master/src/board.rs#L231
+    let score = match m.cmp(&o) {
+        Ordering::Greater => squares - 2 * o,
+        Ordering::Less => -squares + 2 * m,
+        Ordering::Equal => 0,
+    };
+    if ANTI {
+        -score
     } else {
-        // evaluate by score
-        // Using `m - o` as score is not appropriate in order to pass FFO.
-        // https://github.com/primenumber/issen-rs/blob/master/src/board.rs#L231
-        match m.cmp(&o) {
-            Ordering::Greater => 64 - 2 * o,
-            Ordering::Less => -64 + 2 * m,
-            Ordering::Equal => 0,
-        }
+        score
     }
 }
 
@@ -209,12 +988,326 @@ mod tests {
     fn test_find_best_move() {
         let b = Board::new();
         println!("{}", b);
-        assert!(vec![29, 43, 45].contains(&find_best_move(&b, 100)));
+        assert!([29, 43, 45].contains(&find_best_move(&b, 100)));
+    }
+
+    #[test]
+    fn test_score_moves() {
+        let b = Board::new();
+        let scores = score_moves(&b, Rules::Standard, 4, &TimeLimit::fixed(1000));
+        let idx: Vec<u8> = scores.iter().map(|(idx, _)| *idx).collect();
+        assert_eq!(idx, vec![29, 43, 45]);
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<SearchInfo>>);
+
+    impl SearchObserver for Recorder {
+        fn iteration(&self, info: &SearchInfo) {
+            self.0.lock().unwrap().push(info.clone());
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        // a single root move to depth 14 takes far more than one poll, so
+        // both a passed deadline and a set stop flag end it at the first one
+        let options = SearchOptions::default();
+        let stop = AtomicBool::new(true);
+        for abort in [
+            Abort {
+                deadline: Some(Instant::now()),
+                stop: None,
+            },
+            Abort {
+                deadline: None,
+                stop: Some(&stop),
+            },
+        ] {
+            let mut searcher = Searcher::new(&options, 14);
+            let searched = searcher.root_search(&Board::new(), 0, 14, -100, 100, &abort);
+            assert!(searched.is_none());
+            assert_eq!(searcher.stats.nodes, POLL_NODES);
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let b = Board::new();
+        let recorder = Recorder::default();
+        let options = SearchOptions {
+            limit: Some(TimeLimit::fixed(200)),
+            observer: Some(&recorder),
+            ..SearchOptions::default()
+        };
+        let result = iterative_search(&b, &options);
+        let infos = recorder.0.into_inner().unwrap();
+        assert!(!infos.is_empty());
+        let mut stats = SearchStats::default();
+        for info in &infos {
+            stats.merge(&info.stats);
+        }
+        // the aborted iteration is only in the result
+        assert!(stats.nodes <= result.stats.nodes);
+        assert_eq!(infos[0].depth, MIN_DEPTH);
+        let last = infos.last().unwrap();
+        assert_eq!((last.pv[0], last.score), (result.idx, result.score));
+        assert!(last.pv.len() <= last.depth as usize);
+        let mut p = b.clone();
+        for idx in &last.pv {
+            if mobility::get_mobility(&p) == 0 {
+                p = p.pass();
+            }
+            assert_eq!(mobility::get_mobility(&p) >> idx & 1, 1);
+            p = mobility::put(&p, *idx);
+        }
+
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let recorder = Recorder::default();
+        let options = SearchOptions {
+            observer: Some(&recorder),
+            ..SearchOptions::default()
+        };
+        let result = complete_search_with(&b, &options);
+        let info = recorder.0.into_inner().unwrap().pop().unwrap();
+        assert!(info.game_end);
+        assert_eq!((info.pv[0], info.score), (result.idx, result.score));
+        let stats = &result.stats;
+        assert_eq!(stats, &info.stats);
+        assert_eq!(stats.depth_nodes[0], 1);
+        assert_eq!(stats.depth_nodes.iter().sum::<u64>(), stats.nodes);
+        assert!(stats.leaves < stats.nodes);
+        assert!(stats.tt_cuts <= stats.tt_hits);
+        assert!(stats.etc_cuts > 0 && stats.etc_cuts <= stats.etc_nodes);
+        assert!(stats.cutoffs[0] > stats.cutoffs[1]);
+    }
+
+    #[test]
+    fn test_aspiration() {
+        let b = crate::transcript::parse("f5f4e3d2f3g3c3c6e2b4g4f6f7g2d3f2h3h5c5c4")
+            .unwrap()
+            .replay()
+            .unwrap()
+            .pop()
+            .unwrap()
+            .0;
+        let options = SearchOptions::default();
+        let expected = Searcher::new(&options, 8)
+            .root_search(&b, 0, 8, -100, 100, &NO_ABORT)
+            .unwrap();
+        for guess in [expected.score - 20, expected.score, expected.score + 20] {
+            let mut searcher = Searcher::new(&options, 8);
+            let r = searcher
+                .aspiration_search(&b, 8, guess, 1, &NO_ABORT)
+                .unwrap();
+            assert_eq!(r.score, expected.score);
+            assert_eq!(
+                searcher.take_stats().researches > 0,
+                guess != expected.score
+            );
+        }
+    }
+
+    /// Plain negamax on the final disc count, empties to the winner
+    fn minimax<S: Size>(b: &Board<S>, rules: Rules) -> i8 {
+        let moves: Vec<u8> = mobility::legal_moves(b).collect();
+        if moves.is_empty() {
+            let passed = b.pass();
+            if mobility::get_mobility(&passed) == S::Bits::ZERO {
+                let diff = b.me.count_ones() as i8 - b.opp.count_ones() as i8;
+                let score = diff + diff.signum() * b.empties() as i8;
+                return if rules == Rules::Anti { -score } else { score };
+            }
+            return -minimax(&passed, rules);
+        }
+        moves
+            .iter()
+            .map(|idx| -minimax(&mobility::put(b, *idx), rules))
+            .max()
+            .unwrap()
+    }
+
+    /// A random position with `empties` empty squares, or the end of a random game
+    fn random_position<S: Size>(empties: u32, rng: &mut impl rand::Rng) -> Board<S> {
+        let mut b = Board::<S>::initial();
+        while b.empties() > empties {
+            let moves: Vec<u8> = mobility::legal_moves(&b).collect();
+            if moves.is_empty() {
+                b = b.pass();
+                if mobility::get_mobility(&b) == S::Bits::ZERO {
+                    break;
+                }
+                continue;
+            }
+            b = mobility::put(&b, moves[rng.gen_range(0..moves.len())]);
+        }
+        b
+    }
+
+    #[test]
+    fn test_rules() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..10 {
+            let b = random_position::<Size8>(9, &mut rng);
+            for rules in [Rules::Standard, Rules::Anti] {
+                let options = SearchOptions {
+                    rules,
+                    ..SearchOptions::default()
+                };
+                let result = complete_search_with(&b, &options);
+                assert_eq!(result.score, minimax(&b, rules), "{:?}\n{}", rules, b);
+            }
+        }
+
+        // FFO #1 with 14 empties, deep enough for the ordering stages; the
+        // anti score is that of a plain alpha-beta search
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let options = SearchOptions {
+            rules: Rules::Anti,
+            ..SearchOptions::default()
+        };
+        let result = complete_search_with(&b, &options);
+        assert_eq!((result.idx, result.score), (14, 0));
+    }
+
+    #[test]
+    fn test_sizes() {
+        use rand::SeedableRng;
+        // 4x4 is a win for White by 3-11, the 2 empty squares go to White
+        let b = Board::<board::Size4>::initial();
+        assert_eq!(complete_search(&b).score, -10);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..5 {
+            let b = random_position::<board::Size6>(10, &mut rng);
+            assert_eq!(
+                complete_search(&b).score,
+                minimax(&b, Rules::Standard),
+                "{}",
+                b
+            );
+            let b = random_position::<board::Size10>(8, &mut rng);
+            assert_eq!(
+                complete_search(&b).score,
+                minimax(&b, Rules::Standard),
+                "{}",
+                b
+            );
+        }
+
+        // 6x6 is a win for White by 16-20 (Feinstein, 1993), so Black to move
+        // scores -4 all along a line of perfect play; this one was taken from
+        // a 3 hour solve of the initial position
+        let line = [8, 19, 26, 9, 22, 16, 3, 2, 1, 27, 33, 23];
+        let mut b = Board::<board::Size6>::initial();
+        for idx in line {
+            b = mobility::put(&b, idx);
+        }
+        assert_eq!(b.empties(), 20);
+        assert_eq!(complete_search(&b).score, -4);
+    }
+
+    #[test]
+    fn test_ordering() {
+        // FFO #1, where each stage alone decides some of the 14 empties
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let mut orderings: Vec<Vec<OrderingStage>> =
+            ordering::ALL_STAGES.iter().map(|s| vec![*s]).collect();
+        orderings.push(ordering::ALL_STAGES.to_vec());
+        orderings.push(vec![]);
+        for stages in &orderings {
+            let recorder = Recorder::default();
+            let options = SearchOptions {
+                ordering: Some(stages),
+                observer: Some(&recorder),
+                ..SearchOptions::default()
+            };
+            let result = complete_search_with(&b, &options);
+            assert_eq!((result.idx, result.score), (62, 18), "{:?}", stages);
+            let stats = recorder.0.into_inner().unwrap().pop().unwrap().ordering;
+            for stage in stages {
+                assert!(stats.stage(*stage).selected > 0, "{:?}", stage);
+            }
+        }
+
+        let recorder = Recorder::default();
+        let options = SearchOptions {
+            observer: Some(&recorder),
+            ..SearchOptions::default()
+        };
+        complete_search_with(&b, &options);
+        let stats = recorder.0.into_inner().unwrap().pop().unwrap().ordering;
+        assert!(stats.first_cutoffs > 0 && stats.first_cutoffs <= stats.cut_nodes);
+        let selected: u64 = stats.stages.iter().map(|s| s.selected).sum();
+        assert!(stats.stage(OrderingStage::FastestFirst).selected > 0);
+        assert!(stats.stages.iter().all(|s| s.cutoffs <= s.selected) && selected > 0);
+    }
+
+    #[test]
+    fn test_cache() {
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let short = [62, 55, 56, 40]
+            .iter()
+            .fold(b.clone(), |b, idx| mobility::try_put(&b, *idx).unwrap());
+        let mut cache = SolvedCache::new(16);
+        cache.min_empties = 10;
+        let cache = Mutex::new(cache);
+        let options = SearchOptions {
+            cache: Some(&cache),
+            ..SearchOptions::default()
+        };
+        let solved = complete_search_with(&short, &options);
+        assert!(solved.stats.nodes > 0);
+        // a reflection of the position is found without searching
+        let mirrored = short.symmetries()[3].clone();
+        let found = complete_search_with(&mirrored, &options);
+        assert_eq!(found.stats.nodes, 0);
+        assert_eq!(found.score, solved.score);
+        assert_eq!(
+            found.idx,
+            board::transform(1 << solved.idx, 3).trailing_zeros() as u8
+        );
+
+        // where the side to move passes, a hit returns the opponent's reply
+        // as the search does
+        let pass = Board {
+            me: 4500835554627487745,
+            opp: 2481498237892606,
+        };
+        assert_eq!(mobility::get_mobility(&pass), 0);
+        cache.lock().unwrap().min_empties = 7;
+        let searched = complete_search_with(&pass, &options);
+        assert_eq!((searched.idx, searched.score), (24, -48));
+        let found = complete_search_with(&pass, &options);
+        assert_eq!(found.stats.nodes, 0);
+        assert_eq!(
+            (found.idx, found.score, found.game_end),
+            (searched.idx, searched.score, searched.game_end)
+        );
+    }
+
+    #[test]
+    fn test_shared_tt() {
+        let b = board::parse("--XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO--");
+        let short = [62, 55, 56, 40]
+            .iter()
+            .fold(b.clone(), |b, idx| mobility::try_put(&b, *idx).unwrap());
+        let tt = Mutex::new(TranspositionTable::new(tt::bits_for(10)));
+        let options = SearchOptions {
+            tt: Some(&tt),
+            ..SearchOptions::default()
+        };
+        let first = complete_search_with(&short, &options);
+        // the second search starts from the entries of the first
+        let again = complete_search_with(&short, &options);
+        assert_eq!((again.idx, again.score), (first.idx, first.score));
+        assert!(again.stats.nodes < first.stats.nodes);
     }
 
     #[test]
     fn test_one_mobility_check() {
-        let b = Board {
+        let b: Board = Board {
             me: 114633790074399,
             opp: 18446066489965990112,
         };
@@ -0,0 +1,155 @@
+//! Weakened and varied play
+//!
+//! Moves are scored by a shallow search, perturbed by noise,
+//! and one of those close to the best is picked at random.
+
+use super::board::Board;
+use super::game::{Game, GameError, Move, Rules};
+use super::mobility;
+use super::search::{self, SearchOptions, TimeLimit};
+use rand::Rng;
+use std::time::Duration;
+
+pub const MAX_LEVEL: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strength {
+    pub max_depth: u8,
+    pub max_ms: u64,
+    /// Each move score is shifted by a uniform random value in `-noise..=noise`
+    pub noise: i8,
+    /// Moves within this margin of the best noisy score are picked at random
+    pub margin: i8,
+}
+
+impl Strength {
+    /// Level 1 (weakest) to `MAX_LEVEL`, which is a plain deterministic search
+    pub fn level(level: u8) -> Strength {
+        let level = level.clamp(1, MAX_LEVEL);
+        let weakness = (MAX_LEVEL - level) as i8;
+        Strength {
+            max_depth: if level == MAX_LEVEL { 20 } else { level },
+            max_ms: 300 * level as u64,
+            noise: weakness * 2,
+            margin: weakness,
+        }
+    }
+}
+
+/// A move searched within the tighter of `limit` and `strength.max_ms`
+pub fn choose_move<R: Rng>(
+    b: &Board,
+    rules: Rules,
+    strength: &Strength,
+    limit: &TimeLimit,
+    rng: &mut R,
+) -> u8 {
+    let max = Duration::from_millis(strength.max_ms);
+    let limit = TimeLimit {
+        soft: limit.soft.min(max),
+        hard: limit.hard.min(max),
+    };
+    if strength.noise == 0 && strength.margin == 0 {
+        let options = SearchOptions {
+            limit: Some(limit),
+            rules,
+            max_depth: Some(strength.max_depth),
+            ..SearchOptions::default()
+        };
+        return search::iterative_search(b, &options).idx;
+    }
+    let scores: Vec<(u8, i32)> = search::score_moves(b, rules, strength.max_depth, &limit)
+        .into_iter()
+        .map(|(idx, score)| {
+            let noise = strength.noise as i32;
+            (idx, score as i32 + rng.gen_range(-noise..=noise))
+        })
+        .collect();
+    let best = scores.iter().map(|(_, score)| *score).max().unwrap();
+    let candidates: Vec<u8> = scores
+        .iter()
+        .filter(|(_, score)| *score >= best - strength.margin as i32)
+        .map(|(idx, _)| *idx)
+        .collect();
+    candidates[rng.gen_range(0..candidates.len())]
+}
+
+/// Continue the game by `choose_move` for both sides until it ends.
+///
+/// Fails if the moves already in `game` are not legal.
+pub fn play_out<R: Rng>(
+    game: &mut Game,
+    strength: &Strength,
+    rng: &mut R,
+) -> Result<(), GameError> {
+    let (mut b, mut color) = game.replay()?.pop().unwrap();
+    loop {
+        let idx = if mobility::get_mobility(&b) != 0 {
+            Some(choose_move(
+                &b,
+                game.rules,
+                strength,
+                &TimeLimit::fixed(strength.max_ms),
+                rng,
+            ))
+        } else if mobility::get_mobility(&b.pass()) != 0 {
+            None
+        } else {
+            break;
+        };
+        game.moves.push(Move::new(color, idx));
+        b = match idx {
+            Some(idx) => mobility::put(&b, idx),
+            None => b.pass(),
+        };
+        color = color.opposite();
+    }
+    game.score = Some(game.final_score()? as f64);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_choose_move() {
+        let b = Board::new();
+        let pick = |strength: &Strength, seed| {
+            choose_move(
+                &b,
+                Rules::Standard,
+                strength,
+                &TimeLimit::fixed(strength.max_ms),
+                &mut rand::rngs::StdRng::seed_from_u64(seed),
+            )
+        };
+
+        let strongest = Strength::level(MAX_LEVEL);
+        assert_eq!(strongest.noise, 0);
+        assert_eq!(strongest.margin, 0);
+        // without noise the level is a plain search to its depth, which the
+        // time limit leaves room for
+        let plain = Strength {
+            max_depth: 6,
+            max_ms: 60_000,
+            noise: 0,
+            margin: 0,
+        };
+        let options = SearchOptions {
+            max_depth: Some(6),
+            ..SearchOptions::default()
+        };
+        let searched = search::iterative_search(&b, &options).idx;
+        for seed in 0..3 {
+            assert_eq!(pick(&plain, seed), searched);
+        }
+
+        let weak = Strength::level(2);
+        assert_eq!(pick(&weak, 1), pick(&weak, 1));
+        let picked: std::collections::HashSet<u8> = (0..20).map(|seed| pick(&weak, seed)).collect();
+        assert!(picked.len() > 1);
+        assert!(picked.iter().all(|idx| [29, 43, 45].contains(idx)));
+    }
+}
@@ -0,0 +1,222 @@
+//! SVG diagrams of positions and games
+
+use super::board::{self, Board};
+use super::game::{Color, Game, GameError};
+use super::mobility;
+
+const CELL: usize = 50;
+const MARGIN: usize = 30;
+const SIZE: usize = CELL * 8 + MARGIN * 2;
+
+#[derive(Debug, Clone, Default)]
+pub struct SvgOptions {
+    pub last_move: Option<u8>,
+    /// Mark the legal moves of the side to move
+    pub legal_moves: bool,
+    /// Labels drawn on squares, e.g. scores of each move
+    pub labels: Vec<(u8, String)>,
+}
+
+fn center(idx: u8) -> (usize, usize) {
+    (
+        MARGIN + (idx as usize % 8) * CELL + CELL / 2,
+        MARGIN + (idx as usize / 8) * CELL + CELL / 2,
+    )
+}
+
+/// Render a position where `color` is the side to move.
+pub fn render(b: &Board, color: Color, options: &SvgOptions) -> String {
+    let (black, white) = match color {
+        Color::Black => (b.me, b.opp),
+        Color::White => (b.opp, b.me),
+    };
+    let mut s = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#,
+        size = SIZE
+    );
+    s.push_str(&format!(
+        r##"<rect width="{}" height="{}" fill="#f5f5f5"/><rect x="{m}" y="{m}" width="{b}" height="{b}" fill="#2e7d32"/>"##,
+        SIZE,
+        SIZE,
+        m = MARGIN,
+        b = CELL * 8
+    ));
+
+    for i in 0..=8 {
+        let p = MARGIN + i * CELL;
+        s.push_str(&format!(
+            r#"<line x1="{p}" y1="{m}" x2="{p}" y2="{e}" stroke="black"/><line x1="{m}" y1="{p}" x2="{e}" y2="{p}" stroke="black"/>"#,
+            p = p,
+            m = MARGIN,
+            e = MARGIN + CELL * 8
+        ));
+    }
+    for i in 0..8 {
+        let p = MARGIN + i * CELL + CELL / 2;
+        s.push_str(&format!(
+            r#"<text x="{p}" y="{t}" font-family="sans-serif" font-size="16" text-anchor="middle">{c}</text><text x="{t}" y="{p}" font-family="sans-serif" font-size="16" text-anchor="middle" dominant-baseline="central">{r}</text>"#,
+            p = p,
+            t = MARGIN / 2 + 4,
+            c = (b'A' + i as u8) as char,
+            r = i + 1
+        ));
+    }
+
+    for idx in 0..64u8 {
+        let (x, y) = center(idx);
+        let fill = if black >> idx & 1 == 1 {
+            "black"
+        } else if white >> idx & 1 == 1 {
+            "white"
+        } else {
+            continue;
+        };
+        s.push_str(&format!(
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="black"/>"#,
+            x,
+            y,
+            CELL * 2 / 5,
+            fill
+        ));
+    }
+
+    if options.legal_moves {
+        for idx in mobility::legal_moves(b) {
+            let (x, y) = center(idx);
+            s.push_str(&format!(
+                r#"<circle cx="{}" cy="{}" r="4" fill="{}" fill-opacity="0.5"/>"#,
+                x,
+                y,
+                match color {
+                    Color::Black => "black",
+                    Color::White => "white",
+                }
+            ));
+        }
+    }
+    if let Some(idx) = options.last_move {
+        let (x, y) = center(idx);
+        s.push_str(&format!(
+            r#"<circle cx="{}" cy="{}" r="5" fill="red"/>"#,
+            x, y
+        ));
+    }
+    for (idx, label) in &options.labels {
+        let (x, y) = center(*idx);
+        s.push_str(&format!(
+            r##"<text x="{}" y="{}" font-family="sans-serif" font-size="14" text-anchor="middle" dominant-baseline="central" fill="#ffeb3b">{}</text>"##,
+            x,
+            y,
+            escape(label)
+        ));
+    }
+
+    s.push_str("</svg>");
+    s
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One frame per position, from the start to the end of the game
+pub fn render_game(game: &Game) -> Result<Vec<String>, GameError> {
+    let positions = game.replay()?;
+    Ok(positions
+        .iter()
+        .enumerate()
+        .map(|(i, (b, color))| {
+            let options = SvgOptions {
+                last_move: if i == 0 { None } else { game.moves[i - 1].idx },
+                legal_moves: true,
+                ..SvgOptions::default()
+            };
+            render(b, *color, &options)
+        })
+        .collect())
+}
+
+/// A self-contained page stepping through the game by buttons or arrow keys
+pub fn render_game_html(game: &Game) -> Result<String, GameError> {
+    let frames = render_game(game)?;
+    let mut s = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} vs {}</title>\n</head>\n<body>\n",
+        escape(&game.black.name),
+        escape(&game.white.name)
+    );
+    for (i, frame) in frames.iter().enumerate() {
+        let caption = if i == 0 {
+            "start".to_owned()
+        } else {
+            let m = &game.moves[i - 1];
+            format!(
+                "{}. {:?} {}",
+                i,
+                m.color,
+                m.idx
+                    .map(board::square_name)
+                    .unwrap_or_else(|| "pass".to_owned())
+            )
+        };
+        s.push_str(&format!(
+            "<div class=\"frame\" style=\"display:{}\"><p>{}</p>{}</div>\n",
+            if i == 0 { "block" } else { "none" },
+            caption,
+            frame
+        ));
+    }
+    s.push_str(
+        r#"<p><button onclick="step(-1)">&lt;</button> <button onclick="step(1)">&gt;</button></p>
+<script>
+const frames = document.querySelectorAll(".frame");
+let current = 0;
+function step(d) {
+  frames[current].style.display = "none";
+  current = Math.min(Math.max(current + d, 0), frames.length - 1);
+  frames[current].style.display = "block";
+}
+document.addEventListener("keydown", e => {
+  if (e.key === "ArrowLeft") step(-1);
+  if (e.key === "ArrowRight") step(1);
+});
+</script>
+</body>
+</html>
+"#,
+    );
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript;
+
+    #[test]
+    fn test_render() {
+        let options = SvgOptions {
+            last_move: Some(37),
+            legal_moves: true,
+            labels: vec![(19, "+2".to_owned())],
+        };
+        let svg = render(&Board::new(), Color::White, &options);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches(r#"fill="black" stroke="black""#).count(), 4);
+        assert_eq!(svg.matches(r#"fill="white" stroke="black""#).count(), 1);
+        assert_eq!(svg.matches(r#"r="4""#).count(), 3);
+        assert_eq!(svg.matches(r#"fill="red""#).count(), 1);
+        assert!(svg.contains(">+2</text>"));
+    }
+
+    #[test]
+    fn test_render_game() {
+        let game = transcript::parse("f5d6c3").unwrap();
+        assert_eq!(render_game(&game).unwrap().len(), 4);
+        let html = render_game_html(&game).unwrap();
+        assert_eq!(html.matches("<svg").count(), 4);
+        assert!(html.contains("3. Black c3"));
+    }
+}
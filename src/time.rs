@@ -0,0 +1,45 @@
+//! `Instant` of the search deadlines
+//!
+//! `std::time::Instant` panics on wasm32-unknown-unknown, so there the host
+//! has to provide the current time as `env.reversi_now`, in milliseconds,
+//! e.g. by `performance.now()`.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+pub use self::host::Instant;
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    use std::time::Duration;
+
+    #[link(wasm_import_module = "env")]
+    extern "C" {
+        fn reversi_now() -> f64;
+    }
+
+    /// Milliseconds since an origin chosen by the host
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub struct Instant(f64);
+
+    impl Instant {
+        pub fn now() -> Instant {
+            // SAFETY: the import takes no arguments and returns a plain number
+            Instant(unsafe { reversi_now() })
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().saturating_duration_since(*self)
+        }
+
+        pub fn checked_add(&self, d: Duration) -> Option<Instant> {
+            let ms = self.0 + d.as_secs_f64() * 1000.0;
+            ms.is_finite().then_some(Instant(ms))
+        }
+
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            Duration::from_secs_f64((self.0 - earlier.0).max(0.0) / 1000.0)
+        }
+    }
+}
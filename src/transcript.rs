@@ -0,0 +1,134 @@
+//! Move transcripts such as `f5d6c3d3c4`, played from the initial position
+//!
+//! A pass is written as `pa` (or `--`) and may be omitted.
+
+use super::board::{self, Board};
+use super::game::{Color, Game, GameError, Move};
+use super::mobility;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptError {
+    Syntax(String),
+    Game(GameError),
+}
+
+impl std::fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TranscriptError::Syntax(s) => write!(f, "invalid transcript : {}", s),
+            TranscriptError::Game(e) => write!(f, "invalid game : {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TranscriptError {}
+
+impl From<GameError> for TranscriptError {
+    fn from(e: GameError) -> TranscriptError {
+        TranscriptError::Game(e)
+    }
+}
+
+pub fn parse(s: &str) -> Result<Game, TranscriptError> {
+    let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.len() % 2 != 0 {
+        return Err(TranscriptError::Syntax(format!("odd length {}", s)));
+    }
+
+    let mut game = Game::new(Board::initial(), Color::Black);
+    let mut b = game.start.clone();
+    let mut color = game.start_color;
+    for token in chars.chunks(2) {
+        let token: String = token.iter().collect();
+        let idx = if token.eq_ignore_ascii_case("pa") || token == "--" {
+            None
+        } else {
+            match board::parse_square(&token) {
+                Some(idx) => Some(idx),
+                None => return Err(TranscriptError::Syntax(format!("unknown move {}", token))),
+            }
+        };
+
+        let mobility = mobility::get_mobility(&b);
+        if mobility == 0 && mobility::get_mobility(&b.pass()) == 0 {
+            let ply = game.moves.len();
+            return Err(GameError::AfterEnd { ply }.into());
+        }
+        if mobility == 0 && idx.is_some() {
+            // implicit pass
+            game.moves.push(Move::new(color, None));
+            b = b.pass();
+            color = color.opposite();
+        }
+        let ply = game.moves.len();
+        b = match idx {
+            Some(idx) => {
+                mobility::try_put(&b, idx).map_err(|_| GameError::IllegalMove { ply, idx })?
+            }
+            None if mobility == 0 => b.pass(),
+            None => return Err(GameError::IllegalPass { ply }.into()),
+        };
+        game.moves.push(Move::new(color, idx));
+        color = color.opposite();
+    }
+    Ok(game)
+}
+
+/// Moves of the game, with `pa` for passes if `passes` is set
+pub fn serialize(game: &Game, passes: bool) -> String {
+    game.moves
+        .iter()
+        .filter_map(|m| match m.idx {
+            Some(idx) => Some(board::square_name(idx)),
+            None if passes => Some("pa".to_owned()),
+            None => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let game = parse("F5d6C3d3").unwrap();
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(serialize(&game, true), "f5d6c3d3");
+        let (b, color) = game.replay().unwrap().pop().unwrap();
+        assert_eq!(color, Color::Black);
+        assert_eq!(b.me.count_ones() + b.opp.count_ones(), 8);
+
+        assert_eq!(
+            parse("f5f5"),
+            Err(TranscriptError::Game(GameError::IllegalMove {
+                ply: 1,
+                idx: 37
+            }))
+        );
+        assert_eq!(
+            parse("f5pa"),
+            Err(TranscriptError::Game(GameError::IllegalPass { ply: 1 }))
+        );
+        assert!(matches!(parse("f5d"), Err(TranscriptError::Syntax(_))));
+        assert!(matches!(parse("f5z9"), Err(TranscriptError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_pass() {
+        // the shortest game, White is wiped out
+        let without = parse("e6f4e3f6g5d6e7f5c5").unwrap();
+        assert_eq!(without.final_score(), Ok(64));
+        assert_eq!(
+            parse("e6f4e3f6g5d6e7f5c5pa"),
+            Err(TranscriptError::Game(GameError::AfterEnd { ply: 9 }))
+        );
+
+        // a random game including a pass
+        let transcript = "f5f6f7g5c4e3h4c3d3b4f3c5b6d2g6g3e6a7a4e7f2f4d6h7d1g1f8c2h5c1e2a5a6c6e1a3c7d7e8b3b1d8b7h2c8b5a2h6g7b2h3h8a8g4h1b8f1g8g2";
+        let game = parse(transcript).unwrap_or_else(|e| panic!("{}", e));
+        assert!(game.moves.iter().any(|m| m.idx.is_none()));
+        assert_eq!(serialize(&game, false), transcript);
+        assert_eq!(parse(&serialize(&game, true)).unwrap(), game);
+    }
+}
@@ -0,0 +1,118 @@
+//! Transposition table of search results
+//!
+//! A fixed number of slots indexed by a hash of the board; a new result
+//! always replaces the one in its slot.
+
+use super::board::{Bits, Board, Size, Size8};
+use std::fmt;
+
+/// Search result of a position as bounds of its score
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Entry<S: Size = Size8> {
+    me: S::Bits,
+    opp: S::Bits,
+    /// Plies searched below the position
+    pub depth: u8,
+    pub lower: i8,
+    pub upper: i8,
+    /// `u8::MAX` when no move raised alpha
+    pub best: u8,
+    pub game_end: bool,
+}
+
+impl<S: Size> Entry<S> {
+    pub fn new(
+        b: &Board<S>,
+        depth: u8,
+        lower: i8,
+        upper: i8,
+        best: u8,
+        game_end: bool,
+    ) -> Entry<S> {
+        Entry {
+            me: b.me,
+            opp: b.opp,
+            depth,
+            lower,
+            upper,
+            best,
+            game_end,
+        }
+    }
+}
+
+pub struct TranspositionTable<S: Size = Size8> {
+    entries: Vec<Entry<S>>,
+    shift: u32,
+}
+
+impl<S: Size> fmt::Debug for TranspositionTable<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("slots", &self.entries.len())
+            .finish()
+    }
+}
+
+/// 2^20 slots, 24MiB
+pub const DEFAULT_BITS: u32 = 20;
+
+/// Enough slots for a search of `plies`, up to `DEFAULT_BITS`
+pub fn bits_for(plies: u8) -> u32 {
+    (plies as u32 / 2 + 10).min(DEFAULT_BITS)
+}
+
+impl<S: Size> TranspositionTable<S> {
+    pub fn new(bits: u32) -> TranspositionTable<S> {
+        TranspositionTable {
+            entries: vec![Entry::default(); 1 << bits],
+            shift: 64 - bits,
+        }
+    }
+
+    #[inline]
+    fn index(&self, b: &Board<S>) -> usize {
+        let h = b.me.fold().wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ b.opp.fold().wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+        (h.rotate_left(17).wrapping_mul(0x1656_67b1_9e37_79f9) >> self.shift) as usize
+    }
+
+    #[inline]
+    pub fn get(&self, b: &Board<S>) -> Option<&Entry<S>> {
+        let entry = &self.entries[self.index(b)];
+        // an empty slot never matches since a position has discs
+        if entry.me == b.me && entry.opp == b.opp {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn store(&mut self, entry: Entry<S>) {
+        let b = Board {
+            me: entry.me,
+            opp: entry.opp,
+        };
+        let i = self.index(&b);
+        self.entries[i] = entry;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let mut tt: TranspositionTable = TranspositionTable::new(4);
+        let b = Board::new();
+        assert_eq!(tt.get(&b), None);
+        tt.store(Entry::new(&b, 3, -2, 4, 29, false));
+        assert_eq!(
+            tt.get(&b).map(|e| (e.lower, e.upper, e.best)),
+            Some((-2, 4, 29))
+        );
+        assert_eq!(tt.get(&b.pass()), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! A small engine interface for WebAssembly hosts
+//!
+//! `Engine` keeps one game position. On wasm32 it is exported as plain
+//! functions on a single engine, so no bindings generator is needed:
+//!
+//! ```js
+//! const { instance } = await WebAssembly.instantiate(bytes, {
+//!   env: { reversi_now: () => performance.now() },
+//! });
+//! const e = instance.exports;
+//! e.reversi_play(37); // f5
+//! const idx = e.reversi_think(500);
+//! ```
+//!
+//! Bitboards are `u64` with square `y * 8 + x` at bit `y * 8 + x`, as
+//! everywhere else in this crate. JavaScript sees them as signed BigInt,
+//! `BigInt.asUintN(64, x)` gives the unsigned value.
+
+use super::board::Board;
+use super::game::{Color, Rules};
+use super::mobility;
+use super::search::{self, SearchOptions, TimeLimit};
+use super::tt::{self, TranspositionTable};
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub struct Engine {
+    /// From the point of view of `color`
+    board: Board,
+    color: Color,
+    pub rules: Rules,
+    /// Of the last `think`, from the point of view of the side which thought
+    score: i8,
+    /// Kept between `think` calls under `tt_rules`
+    tt: Mutex<TranspositionTable>,
+    tt_rules: Rules,
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// The initial position, Black to move
+    pub fn new() -> Engine {
+        Engine {
+            board: Board::initial(),
+            color: Color::Black,
+            rules: Rules::Standard,
+            score: 0,
+            tt: Mutex::new(TranspositionTable::new(tt::DEFAULT_BITS)),
+            tt_rules: Rules::Standard,
+        }
+    }
+
+    /// The side to move passes if it has no move while the other side has one.
+    ///
+    /// False, keeping the position, if a square holds discs of both colors.
+    pub fn set_position(&mut self, black: u64, white: u64, color: Color) -> bool {
+        if black & white != 0 {
+            return false;
+        }
+        let b = Board {
+            me: black,
+            opp: white,
+        };
+        self.board = match color {
+            Color::Black => b,
+            Color::White => b.pass(),
+        };
+        self.color = color;
+        self.pass_if_needed();
+        true
+    }
+
+    pub fn black(&self) -> u64 {
+        match self.color {
+            Color::Black => self.board.me,
+            Color::White => self.board.opp,
+        }
+    }
+
+    pub fn white(&self) -> u64 {
+        match self.color {
+            Color::Black => self.board.opp,
+            Color::White => self.board.me,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// No legal move means the game is over, as passes are played automatically
+    pub fn legal_moves(&self) -> u64 {
+        mobility::get_mobility(&self.board)
+    }
+
+    /// False if the move is illegal
+    pub fn play(&mut self, idx: u8) -> bool {
+        match mobility::try_put(&self.board, idx) {
+            Ok(b) => {
+                self.board = b;
+                self.color = self.color.opposite();
+                self.pass_if_needed();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// The best move found within `ms`, without playing it.
+    ///
+    /// The search checks the time every 1024 nodes, so it returns that many
+    /// nodes after the deadline at the latest. None when the game is over.
+    pub fn think(&mut self, ms: u64) -> Option<u8> {
+        if self.legal_moves() == 0 {
+            return None;
+        }
+        if self.tt_rules != self.rules {
+            // the entries are scored under the other rules
+            self.tt = Mutex::new(TranspositionTable::new(tt::DEFAULT_BITS));
+            self.tt_rules = self.rules;
+        }
+        let options = SearchOptions {
+            limit: Some(TimeLimit::fixed(ms)),
+            rules: self.rules,
+            tt: Some(&self.tt),
+            ..SearchOptions::default()
+        };
+        let result = search::iterative_search(&self.board, &options);
+        self.score = result.score;
+        Some(result.idx)
+    }
+
+    pub fn score(&self) -> i8 {
+        self.score
+    }
+
+    fn pass_if_needed(&mut self) {
+        if mobility::get_mobility(&self.board) == 0
+            && mobility::get_mobility(&self.board.pass()) != 0
+        {
+            self.board = self.board.pass();
+            self.color = self.color.opposite();
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use super::Engine;
+    use crate::game::Color;
+    use once_cell::sync::Lazy;
+    use std::sync::Mutex;
+
+    static ENGINE: Lazy<Mutex<Engine>> = Lazy::new(|| Mutex::new(Engine::new()));
+
+    fn engine() -> std::sync::MutexGuard<'static, Engine> {
+        ENGINE.lock().unwrap()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn reversi_reset() {
+        *engine() = Engine::new();
+    }
+
+    /// 1 if the position was set, 0 if the bitboards overlap
+    #[no_mangle]
+    pub extern "C" fn reversi_set_position(black: u64, white: u64, white_to_move: u32) -> u32 {
+        let color = if white_to_move != 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        engine().set_position(black, white, color) as u32
+    }
+
+    #[no_mangle]
+    pub extern "C" fn reversi_black() -> u64 {
+        engine().black()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn reversi_white() -> u64 {
+        engine().white()
+    }
+
+    /// 1 if White is to move
+    #[no_mangle]
+    pub extern "C" fn reversi_white_to_move() -> u32 {
+        (engine().color() == Color::White) as u32
+    }
+
+    #[no_mangle]
+    pub extern "C" fn reversi_legal_moves() -> u64 {
+        engine().legal_moves()
+    }
+
+    /// 1 if the move was legal and played
+    #[no_mangle]
+    pub extern "C" fn reversi_play(idx: u32) -> u32 {
+        (idx < 64 && engine().play(idx as u8)) as u32
+    }
+
+    /// The best move, -1 when the game is over
+    #[no_mangle]
+    pub extern "C" fn reversi_think(ms: u32) -> i32 {
+        engine().think(ms as u64).map_or(-1, |idx| idx as i32)
+    }
+
+    /// Score of the last `reversi_think` for the side which thought
+    #[no_mangle]
+    pub extern "C" fn reversi_score() -> i32 {
+        engine().score() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.legal_moves().count_ones(), 4);
+        assert!(!engine.play(0));
+        assert!(engine.play(37));
+        assert_eq!(engine.color(), Color::White);
+        assert_eq!((engine.black() | engine.white()).count_ones(), 5);
+        let idx = engine.think(100).unwrap();
+        assert!(engine.legal_moves() >> idx & 1 != 0);
+
+        let before = (engine.black(), engine.white());
+        assert!(!engine.set_position(3, 2, Color::Black));
+        assert_eq!((engine.black(), engine.white()), before);
+
+        // a1 Black, b1 White: White has no move, so Black continues
+        assert!(engine.set_position(1, 2, Color::White));
+        assert_eq!(engine.color(), Color::Black);
+        assert_eq!(engine.legal_moves(), 4);
+        assert!(engine.play(2));
+        assert_eq!((engine.black(), engine.white()), (7, 0));
+        assert_eq!(engine.think(100), None);
+    }
+}
@@ -0,0 +1,276 @@
+//! WTHOR databases by the Fédération Française d'Othello
+//!
+//! https://www.ffothello.org/informatique/la-base-wthor/
+
+use super::board::Board;
+use super::game::{Color, Game, GameError, Move};
+use super::mobility;
+use std::io::Read;
+
+const HEADER_SIZE: usize = 16;
+const GAME_SIZE: usize = 68;
+/// Record size of `.jou` files
+pub const PLAYER_NAME_SIZE: usize = 20;
+/// Record size of `.trn` files
+pub const TOURNAMENT_NAME_SIZE: usize = 26;
+
+#[derive(Debug)]
+pub enum WthorError {
+    Io(std::io::Error),
+    Format(String),
+    Game(GameError),
+}
+
+impl std::fmt::Display for WthorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WthorError::Io(e) => write!(f, "failed to read WTHOR : {}", e),
+            WthorError::Format(s) => write!(f, "invalid WTHOR : {}", s),
+            WthorError::Game(e) => write!(f, "invalid game : {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WthorError {}
+
+impl From<std::io::Error> for WthorError {
+    fn from(e: std::io::Error) -> WthorError {
+        WthorError::Io(e)
+    }
+}
+
+impl From<GameError> for WthorError {
+    fn from(e: GameError) -> WthorError {
+        WthorError::Game(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Creation date as (year, month, day)
+    pub created: (u16, u8, u8),
+    /// Number of games in a `.wtb` file
+    pub games: u32,
+    /// Number of names in a `.jou` or `.trn` file
+    pub records: u16,
+    pub year: u16,
+    pub board_size: u8,
+    pub solitaire: bool,
+    /// Depth from which the theoretical score is computed
+    pub theoretical_depth: u8,
+}
+
+fn parse_header(bytes: &[u8; HEADER_SIZE]) -> Result<Header, WthorError> {
+    let header = Header {
+        created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+        games: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        records: u16::from_le_bytes([bytes[8], bytes[9]]),
+        year: u16::from_le_bytes([bytes[10], bytes[11]]),
+        board_size: if bytes[12] == 0 { 8 } else { bytes[12] },
+        solitaire: bytes[13] == 1,
+        theoretical_depth: bytes[14],
+    };
+    if header.board_size != 8 {
+        return Err(WthorError::Format(format!(
+            "unsupported board size {}",
+            header.board_size
+        )));
+    }
+    Ok(header)
+}
+
+/// A game as stored in a `.wtb` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub tournament: u16,
+    pub black: u16,
+    pub white: u16,
+    /// Black discs at the end, empty squares going to the winner
+    pub black_discs: u8,
+    /// Black discs by perfect play from `theoretical_depth` empties
+    pub theoretical_discs: u8,
+    /// Moves without passes
+    pub moves: Vec<u8>,
+}
+
+fn parse_record(bytes: &[u8; GAME_SIZE]) -> Result<Record, WthorError> {
+    let mut moves = vec![];
+    for &m in bytes[8..].iter().take_while(|&&m| m != 0) {
+        let (x, y) = (m % 10, m / 10);
+        if !(1..=8).contains(&x) || !(1..=8).contains(&y) {
+            return Err(WthorError::Format(format!("unknown move {}", m)));
+        }
+        moves.push((y - 1) * 8 + (x - 1));
+    }
+    Ok(Record {
+        tournament: u16::from_le_bytes([bytes[0], bytes[1]]),
+        black: u16::from_le_bytes([bytes[2], bytes[3]]),
+        white: u16::from_le_bytes([bytes[4], bytes[5]]),
+        black_discs: bytes[6],
+        theoretical_discs: bytes[7],
+        moves,
+    })
+}
+
+impl Record {
+    /// Replay the moves from the initial position, inserting passes.
+    ///
+    /// Names are looked up by number and left empty when unknown.
+    pub fn to_game(&self, players: &[String], tournaments: &[String]) -> Result<Game, GameError> {
+        let name = |names: &[String], i: u16| names.get(i as usize).cloned().unwrap_or_default();
+        let mut game = Game::new(Board::initial(), Color::Black);
+        game.black.name = name(players, self.black);
+        game.white.name = name(players, self.white);
+        game.place = tournaments.get(self.tournament as usize).cloned();
+        game.score = Some(self.black_discs as f64 * 2.0 - 64.0);
+
+        let mut b = game.start.clone();
+        let mut color = Color::Black;
+        for &idx in &self.moves {
+            if mobility::get_mobility(&b) == 0 {
+                game.moves.push(Move::new(color, None));
+                b = b.pass();
+                color = color.opposite();
+            }
+            let ply = game.moves.len();
+            b = mobility::try_put(&b, idx).map_err(|_| GameError::IllegalMove { ply, idx })?;
+            game.moves.push(Move::new(color, Some(idx)));
+            color = color.opposite();
+        }
+        Ok(game)
+    }
+}
+
+/// Iterator over the games of a `.wtb` file
+pub struct Reader<R: Read> {
+    pub header: Header,
+    reader: R,
+    remaining: u32,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(mut reader: R) -> Result<Reader<R>, WthorError> {
+        let mut bytes = [0; HEADER_SIZE];
+        reader.read_exact(&mut bytes)?;
+        let header = parse_header(&bytes)?;
+        Ok(Reader {
+            remaining: header.games,
+            header,
+            reader,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Record, WthorError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let mut bytes = [0; GAME_SIZE];
+        if let Err(e) = self.reader.read_exact(&mut bytes) {
+            self.remaining = 0;
+            return Some(Err(e.into()));
+        }
+        Some(parse_record(&bytes))
+    }
+}
+
+pub fn open(path: &str) -> Result<Reader<std::io::BufReader<std::fs::File>>, WthorError> {
+    Reader::new(std::io::BufReader::new(std::fs::File::open(path)?))
+}
+
+/// Names in a `.jou` or `.trn` file, decoded as Latin-1
+pub fn read_names(mut reader: impl Read, record_size: usize) -> Result<Vec<String>, WthorError> {
+    let mut bytes = [0; HEADER_SIZE];
+    reader.read_exact(&mut bytes)?;
+    let header = parse_header(&bytes)?;
+    let mut names = vec![];
+    let mut record = vec![0; record_size];
+    for _ in 0..header.records {
+        reader.read_exact(&mut record)?;
+        names.push(
+            record
+                .iter()
+                .take_while(|&&c| c != 0)
+                .map(|&c| c as char)
+                .collect::<String>()
+                .trim_end()
+                .to_owned(),
+        );
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(games: u32, records: u16) -> Vec<u8> {
+        let mut bytes = vec![20, 21, 6, 1];
+        bytes.extend(games.to_le_bytes());
+        bytes.extend(records.to_le_bytes());
+        bytes.extend(2021u16.to_le_bytes());
+        bytes.extend([8, 0, 22, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_reader() {
+        // a random game including a pass
+        #[rustfmt::skip]
+        let moves = [
+            56, 66, 76, 57, 43, 35, 48, 33, 34, 42, 36, 53, 62, 24, 67, 37, 65, 71, 41, 75,
+            26, 46, 64, 78, 14, 17, 86, 23, 58, 13, 25, 51, 61, 63, 15, 31, 73, 74, 85, 32,
+            12, 84, 72, 28, 83, 52, 21, 68, 77, 22, 38, 88, 81, 47, 18, 82, 16, 87, 27,
+        ];
+        let mut bytes = header(2, 0);
+        for m in [&moves[..], &[56, 11]] {
+            bytes.extend(3u16.to_le_bytes());
+            bytes.extend(1u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend([23, 24]);
+            let mut record = m.to_vec();
+            record.resize(60, 0);
+            bytes.extend(record);
+        }
+
+        let mut reader = Reader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.header.created, (2021, 6, 1));
+        assert_eq!(reader.header.year, 2021);
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(record.black, 1);
+        assert_eq!(record.moves[0], 37);
+
+        let players = vec!["white".to_owned(), "black".to_owned()];
+        let game = record.to_game(&players, &[]).unwrap();
+        assert_eq!(game.black.name, "black");
+        assert_eq!(game.white.name, "white");
+        assert_eq!(game.score, Some(-18.0));
+        assert_eq!(game.moves.len(), 60);
+        assert_eq!(game.final_score(), Ok(-18));
+
+        let illegal = reader.next().unwrap().unwrap();
+        assert_eq!(
+            illegal.to_game(&players, &[]),
+            Err(GameError::IllegalMove { ply: 1, idx: 0 })
+        );
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_read_names() {
+        let mut bytes = header(0, 2);
+        for name in ["Tastet Marc", "Anonyme"] {
+            let mut record = name.as_bytes().to_vec();
+            record.resize(PLAYER_NAME_SIZE, 0);
+            bytes.extend(record);
+        }
+        assert_eq!(
+            read_names(&bytes[..], PLAYER_NAME_SIZE).unwrap(),
+            vec!["Tastet Marc", "Anonyme"]
+        );
+    }
+}